@@ -0,0 +1,92 @@
+use chrono::{DateTime, Duration, Utc};
+use std::{error::Error, fmt, str::FromStr};
+
+/// Decides how many days the game advances every time it is updated.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Clock {
+    /// Advances one day for every day passed in the real world since the clock
+    /// was last synced.
+    RealTime { synced: DateTime<Utc> },
+    /// Advances a fixed number of days on every update.
+    Fixed { days_per_update: u32 },
+    /// Never advances on updates, time only moves when explicitly advanced.
+    Manual,
+}
+
+impl Clock {
+    /// Creates a real time clock synced to the current time.
+    pub fn real_time() -> Self {
+        Clock::RealTime { synced: Utc::now() }
+    }
+
+    /// Returns the number of days to advance given the current real time,
+    /// syncing the clock to it.
+    pub fn tick(&mut self, now: DateTime<Utc>) -> u32 {
+        match *self {
+            Clock::RealTime { ref mut synced } => {
+                let days = now.signed_duration_since(*synced).num_days().max(0);
+                *synced = *synced + Duration::days(days);
+                days as u32
+            }
+            Clock::Fixed { days_per_update } => days_per_update,
+            Clock::Manual => 0,
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::real_time()
+    }
+}
+
+/// Error returned when parsing an unknown kind of clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownClock(String);
+
+impl fmt::Display for UnknownClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unknown clock: {}, expected real-time, manual or fixed:<days>",
+            self.0
+        )
+    }
+}
+
+impl Error for UnknownClock {}
+
+impl FromStr for Clock {
+    type Err = UnknownClock;
+
+    /// Parses a clock given as "real-time", "manual" or "fixed:<days>".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || UnknownClock(String::from(s));
+        match s.to_lowercase().as_str() {
+            "real-time" | "realtime" => Ok(Clock::real_time()),
+            "manual" => Ok(Clock::Manual),
+            fixed if fixed.starts_with("fixed:") => Ok(Clock::Fixed {
+                days_per_update: fixed["fixed:".len()..].parse().map_err(|_| unknown())?,
+            }),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick() {
+        let start = Utc::now();
+        let mut clock = Clock::RealTime { synced: start };
+        assert_eq!(clock.tick(start + Duration::hours(30)), 1);
+        assert_eq!(clock.tick(start + Duration::hours(47)), 0);
+        assert_eq!(clock.tick(start + Duration::days(5)), 4);
+
+        assert_eq!("fixed:3".parse::<Clock>().unwrap().tick(start), 3);
+        assert_eq!("manual".parse::<Clock>().unwrap().tick(start), 0);
+        assert!("fixed:x".parse::<Clock>().is_err());
+    }
+}
@@ -1,13 +1,306 @@
+use std::{error::Error, fmt, str::FromStr};
+use toml::{self, Value};
+
+use entities::{Faction, SystemSecurity};
+use utils::Point;
+
 // Deriving `Serialize` and `Deserialize` on a struct/enum automatically
 // implements the `Preferences` trait.
 /// Contains high level configuration parameters for the game such as constants
 /// for generation.
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
 pub struct GameConfig {
     pub map_seed: u32,
     pub number_of_systems: u64,
     pub system_spread: f64,
     pub number_of_sectors: usize,
+    /// Smallest distance between any two systems.
+    pub min_system_distance: f64,
+    pub shape: GalaxyShape,
+    pub stars: StarConfig,
+    pub planets: PlanetConfig,
+    pub factions: FactionsConfig,
+    pub economy: EconomyConfig,
+}
+
+impl GameConfig {
+    /// Parses and validates a config in TOML format, reporting every unknown,
+    /// invalid or inconsistent field.
+    pub fn from_toml(content: &str) -> Result<GameConfig, InvalidConfig> {
+        let value: Value = toml::from_str(content).map_err(|e| InvalidConfig::single("", e))?;
+        let config: GameConfig = value
+            .clone()
+            .try_into()
+            .map_err(|e| InvalidConfig::single("", e))?;
+
+        // Unknown fields would otherwise be silently ignored, compare against
+        // the parsed config since it contains every known field.
+        let mut errors = vec![];
+        let reference = Value::try_from(&config).unwrap();
+        unknown_fields(&value, &reference, "", &mut errors);
+        if let Err(InvalidConfig(invalid)) = config.validate() {
+            errors.extend(invalid);
+        }
+        if !errors.is_empty() {
+            return Err(InvalidConfig(errors));
+        }
+        Ok(config)
+    }
+
+    /// Creates the default config with the size of the galaxy set by the
+    /// preset, keeping the density of systems the same.
+    pub fn preset(preset: Preset) -> GameConfig {
+        let (number_of_systems, system_spread, number_of_sectors) = match preset {
+            Preset::Small => (1_000, 50., 20),
+            Preset::Medium => (10_000, 150., 150),
+            Preset::Huge => (100_000, 475., 1_000),
+        };
+        GameConfig {
+            number_of_systems,
+            system_spread,
+            number_of_sectors,
+            ..GameConfig::default()
+        }
+    }
+
+    /// Serializes the config to TOML with a comment explaining each field.
+    pub fn to_documented_toml(&self) -> String {
+        let content = toml::to_string(self).unwrap();
+        let mut documented = String::from(
+            "# Gemini generation config, every field is optional and falls back to\n\
+             # its default value. Check changes with `gemini check-config`.\n\n",
+        );
+        let mut table = String::new();
+        let mut tables: Vec<String> = vec![];
+        for line in content.lines() {
+            let mut paths = vec![];
+            if line.starts_with('[') {
+                table = String::from(line.trim_matches(|c| c == '[' || c == ']'));
+
+                // Document every enclosing table once, including those which
+                // have no header of their own.
+                let mut prefix = String::new();
+                for part in table.split('.') {
+                    if !prefix.is_empty() {
+                        prefix.push('.');
+                    }
+                    prefix.push_str(part);
+                    if !tables.contains(&prefix) {
+                        tables.push(prefix.clone());
+                        paths.push(prefix.clone());
+                    }
+                }
+                if !documented.ends_with("\n\n") {
+                    documented.push('\n');
+                }
+            } else if let Some(index) = line.find(" = ") {
+                let key = &line[..index];
+                paths.push(if table.is_empty() {
+                    String::from(key)
+                } else {
+                    format!("{}.{}", table, key)
+                });
+            }
+            for doc in paths.iter().filter_map(|path| field_doc(path)) {
+                for doc_line in doc.lines() {
+                    documented.push_str(&format!("# {}\n", doc_line));
+                }
+            }
+            documented.push_str(line);
+            documented.push('\n');
+        }
+        documented
+    }
+
+    /// Checks that all values are within range and consistent with each other.
+    pub fn validate(&self) -> Result<(), InvalidConfig> {
+        let mut v = Validator::default();
+        v.check(
+            self.number_of_systems > 0,
+            "number_of_systems",
+            "must be at least 1",
+        );
+        v.positive(self.system_spread, "system_spread");
+        let min_spread = self.number_of_systems as f64 * Point::HASH_CELL;
+        v.check(
+            self.system_spread >= min_spread,
+            "system_spread",
+            &format!(
+                "must be at least {} to give every system a distinct hash",
+                min_spread
+            ),
+        );
+        v.check(
+            self.number_of_sectors > 0,
+            "number_of_sectors",
+            "must be at least 1",
+        );
+        v.check(
+            self.number_of_sectors as u64 <= self.number_of_systems,
+            "number_of_sectors",
+            &format!(
+                "must be at most number_of_systems ({})",
+                self.number_of_systems
+            ),
+        );
+        v.non_negative(self.min_system_distance, "min_system_distance");
+
+        match self.shape {
+            GalaxyShape::Gaussian => {}
+            GalaxyShape::Elliptical(ref shape) => {
+                v.positive(shape.ratio, "shape.ratio");
+                v.finite(shape.rotation, "shape.rotation");
+            }
+            GalaxyShape::Spiral(ref shape) => {
+                v.check(shape.arms > 0, "shape.arms", "must be at least 1");
+                v.finite(shape.twist, "shape.twist");
+                v.fraction(shape.bulge, "shape.bulge");
+                v.positive(shape.bulge_size, "shape.bulge_size");
+                v.positive(shape.arm_width, "shape.arm_width");
+            }
+            GalaxyShape::Ring(ref shape) => {
+                v.positive(shape.radius, "shape.radius");
+                v.positive(shape.width, "shape.width");
+            }
+            GalaxyShape::Clustered(ref shape) => {
+                v.check(shape.clusters > 0, "shape.clusters", "must be at least 1");
+                v.positive(shape.cluster_spread, "shape.cluster_spread");
+            }
+        }
+
+        v.positive(self.stars.mass_shape, "stars.mass_shape");
+        v.positive(self.stars.mass_rate, "stars.mass_rate");
+        v.positive(self.stars.min_mass, "stars.min_mass");
+        v.fraction(self.stars.binary_chance, "stars.binary_chance");
+        v.fraction(self.stars.trinary_chance, "stars.trinary_chance");
+        v.check(
+            self.stars.binary_chance + self.stars.trinary_chance <= 1.,
+            "stars.trinary_chance",
+            &format!(
+                "must be at most 1 - stars.binary_chance ({})",
+                1. - self.stars.binary_chance
+            ),
+        );
+        v.positive(self.stars.min_separation, "stars.min_separation");
+        v.check(
+            self.stars.max_separation >= self.stars.min_separation,
+            "stars.max_separation",
+            &format!(
+                "must be at least stars.min_separation ({})",
+                self.stars.min_separation
+            ),
+        );
+        v.fraction(self.stars.giant_chance, "stars.giant_chance");
+        v.fraction(self.stars.white_dwarf_chance, "stars.white_dwarf_chance");
+        v.fraction(self.stars.neutron_star_chance, "stars.neutron_star_chance");
+        v.fraction(self.stars.black_hole_chance, "stars.black_hole_chance");
+        let remnant_chance = self.stars.white_dwarf_chance
+            + self.stars.neutron_star_chance
+            + self.stars.black_hole_chance;
+        v.check(
+            remnant_chance <= 1.,
+            "stars",
+            &format!(
+                "chances of white dwarfs, neutron stars and black holes must sum to at most 1, got {}",
+                remnant_chance
+            ),
+        );
+
+        v.positive(self.planets.mean_count, "planets.mean_count");
+        v.positive(self.planets.mean_mass, "planets.mean_mass");
+        v.non_negative(
+            self.planets.min_orbit_distance,
+            "planets.min_orbit_distance",
+        );
+        v.non_negative(self.planets.mean_moons, "planets.mean_moons");
+        v.non_negative(self.planets.mean_giant_moons, "planets.mean_giant_moons");
+        v.check(
+            (0. ..1.).contains(&self.planets.belt_chance),
+            "planets.belt_chance",
+            &format!(
+                "must be at least 0 and less than 1, got {}",
+                self.planets.belt_chance
+            ),
+        );
+
+        let factions = [
+            ("cartel", &self.factions.cartel),
+            ("empire", &self.factions.empire),
+            ("federation", &self.factions.federation),
+            ("independent", &self.factions.independent),
+        ];
+        for (name, faction) in &factions {
+            let path = format!("factions.{}", name);
+            v.non_negative(faction.weight, &format!("{}.weight", path));
+            v.check(
+                !faction.security.is_empty(),
+                &format!("{}.security", path),
+                "must list at least one security level",
+            );
+            for (index, security) in faction.security.iter().enumerate() {
+                v.non_negative(
+                    security.chance,
+                    &format!("{}.security[{}].chance", path, index),
+                );
+            }
+            let total = faction
+                .security
+                .iter()
+                .map(|security| security.chance)
+                .sum::<f64>();
+            v.check(
+                faction.security.is_empty() || (total - 1.).abs() < 1e-6,
+                &format!("{}.security", path),
+                &format!("chances must sum to 1, got {}", total),
+            );
+        }
+        v.check(
+            factions.iter().any(|(_, faction)| faction.weight > 0.),
+            "factions",
+            "at least one faction must have a positive weight",
+        );
+
+        let economy = &self.economy;
+        v.check(
+            economy.min_price > 0,
+            "economy.min_price",
+            "must be at least 1",
+        );
+        v.check(
+            economy.min_price < economy.max_price,
+            "economy.max_price",
+            &format!(
+                "must be greater than economy.min_price ({})",
+                economy.min_price
+            ),
+        );
+        v.check(
+            economy.initial_lower_belief >= economy.min_price,
+            "economy.initial_lower_belief",
+            &format!("must be at least economy.min_price ({})", economy.min_price),
+        );
+        v.check(
+            economy.initial_upper_belief <= economy.max_price,
+            "economy.initial_upper_belief",
+            &format!("must be at most economy.max_price ({})", economy.max_price),
+        );
+        v.check(
+            economy.initial_lower_belief < economy.initial_upper_belief,
+            "economy.initial_upper_belief",
+            &format!(
+                "must be greater than economy.initial_lower_belief ({})",
+                economy.initial_lower_belief
+            ),
+        );
+        v.non_negative(economy.population_factor, "economy.population_factor");
+
+        if v.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidConfig(v.errors))
+        }
+    }
 }
 
 impl Default for GameConfig {
@@ -17,6 +310,581 @@ impl Default for GameConfig {
             number_of_systems: 10_000,
             system_spread: 150.,
             number_of_sectors: 150,
+            min_system_distance: 1.,
+            shape: GalaxyShape::default(),
+            stars: StarConfig::default(),
+            planets: PlanetConfig::default(),
+            factions: FactionsConfig::default(),
+            economy: EconomyConfig::default(),
+        }
+    }
+}
+
+/// Sizes of galaxy to start tuning a config from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    Small,
+    Medium,
+    Huge,
+}
+
+/// Error returned when parsing an unknown preset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownPreset(String);
+
+impl fmt::Display for UnknownPreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unknown preset: {}, expected small, medium or huge",
+            self.0
+        )
+    }
+}
+
+impl Error for UnknownPreset {}
+
+impl FromStr for Preset {
+    type Err = UnknownPreset;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "small" => Ok(Preset::Small),
+            "medium" => Ok(Preset::Medium),
+            "huge" => Ok(Preset::Huge),
+            _ => Err(UnknownPreset(String::from(s))),
+        }
+    }
+}
+
+/// Shape of the galaxy which the systems are placed in. Distances are given
+/// relative to the system spread.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum GalaxyShape {
+    /// Single gaussian blob around the galactic center.
+    #[default]
+    Gaussian,
+    Elliptical(EllipticalShape),
+    Spiral(SpiralShape),
+    Ring(RingShape),
+    Clustered(ClusteredShape),
+}
+
+/// Gaussian blob stretched along one axis.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct EllipticalShape {
+    /// Ratio of the minor axis to the major axis.
+    pub ratio: f64,
+    /// Rotation of the major axis, in degrees.
+    pub rotation: f64,
+}
+
+impl Default for EllipticalShape {
+    fn default() -> EllipticalShape {
+        EllipticalShape {
+            ratio: 0.5,
+            rotation: 0.,
+        }
+    }
+}
+
+/// Central bulge with arms winding out from it to twice the system spread.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct SpiralShape {
+    pub arms: u32,
+    /// Angle in radians which the arms turn per system spread of distance.
+    pub twist: f64,
+    /// Fraction of the systems placed in the central bulge.
+    pub bulge: f64,
+    /// Standard deviation of the bulge.
+    pub bulge_size: f64,
+    /// Standard deviation of systems from the center of their arm.
+    pub arm_width: f64,
+}
+
+impl Default for SpiralShape {
+    fn default() -> SpiralShape {
+        SpiralShape {
+            arms: 2,
+            twist: 2.,
+            bulge: 0.15,
+            bulge_size: 0.3,
+            arm_width: 0.12,
+        }
+    }
+}
+
+/// Ring of systems around an empty center.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct RingShape {
+    pub radius: f64,
+    /// Standard deviation of systems from the ring.
+    pub width: f64,
+}
+
+impl Default for RingShape {
+    fn default() -> RingShape {
+        RingShape {
+            radius: 2.,
+            width: 0.2,
+        }
+    }
+}
+
+/// Clusters of systems spread around the galactic center.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct ClusteredShape {
+    pub clusters: u32,
+    /// Standard deviation of systems from the center of their cluster.
+    pub cluster_spread: f64,
+}
+
+impl Default for ClusteredShape {
+    fn default() -> ClusteredShape {
+        ClusteredShape {
+            clusters: 20,
+            cluster_spread: 0.15,
+        }
+    }
+}
+
+/// Parameters for star generation, star masses follow a Gamma distribution.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct StarConfig {
+    pub mass_shape: f64,
+    pub mass_rate: f64,
+    /// Smallest mass of any star, in solar masses.
+    pub min_mass: f64,
+    /// Chance of a system having two stars.
+    pub binary_chance: f64,
+    /// Chance of a system having three stars.
+    pub trinary_chance: f64,
+    /// Smallest orbital separation of a companion star, in light seconds.
+    pub min_separation: f64,
+    /// Largest orbital separation of a companion star, in light seconds.
+    pub max_separation: f64,
+    /// Chance of a star heavy enough to have left the main sequence being a
+    /// giant, or a supergiant if heavier still.
+    pub giant_chance: f64,
+    pub white_dwarf_chance: f64,
+    pub neutron_star_chance: f64,
+    pub black_hole_chance: f64,
+}
+
+impl Default for StarConfig {
+    fn default() -> StarConfig {
+        StarConfig {
+            mass_shape: 2.,
+            mass_rate: 1.5,
+            min_mass: 0.1,
+            binary_chance: 0.25,
+            trinary_chance: 0.05,
+            min_separation: 10.,
+            max_separation: 50_000.,
+            giant_chance: 0.05,
+            white_dwarf_chance: 0.06,
+            neutron_star_chance: 0.005,
+            black_hole_chance: 0.001,
+        }
+    }
+}
+
+/// Parameters for planet generation.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct PlanetConfig {
+    /// Mean of the Poisson distributed number of planets in a system.
+    pub mean_count: f64,
+    /// Smallest number of planets in a system.
+    pub min_count: u32,
+    /// Mean of the exponentially distributed planet mass.
+    pub mean_mass: f64,
+    /// Minimal distance from a planet to its star.
+    pub min_orbit_distance: f64,
+    /// Mean of the Poisson distributed number of moons of a planet.
+    pub mean_moons: f64,
+    /// Mean of the Poisson distributed number of moons of a gas giant.
+    pub mean_giant_moons: f64,
+    /// Chance of a system having an asteroid belt, and of each further belt.
+    pub belt_chance: f64,
+}
+
+impl Default for PlanetConfig {
+    fn default() -> PlanetConfig {
+        PlanetConfig {
+            mean_count: 3.,
+            min_count: 1,
+            mean_mass: 3.,
+            min_orbit_distance: 500.,
+            mean_moons: 0.5,
+            mean_giant_moons: 4.,
+            belt_chance: 0.4,
+        }
+    }
+}
+
+/// Parameters for each faction.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct FactionsConfig {
+    pub cartel: FactionConfig,
+    pub empire: FactionConfig,
+    pub federation: FactionConfig,
+    pub independent: FactionConfig,
+}
+
+impl FactionsConfig {
+    /// Returns the config of the given faction.
+    pub fn get(&self, faction: &Faction) -> &FactionConfig {
+        match *faction {
+            Faction::Cartel => &self.cartel,
+            Faction::Empire => &self.empire,
+            Faction::Federation => &self.federation,
+            Faction::Independent => &self.independent,
+        }
+    }
+}
+
+impl Default for FactionsConfig {
+    fn default() -> FactionsConfig {
+        FactionsConfig {
+            cartel: FactionConfig::new(
+                15.,
+                &[
+                    (SystemSecurity::Medium, 0.5),
+                    (SystemSecurity::Anarchy, 0.5),
+                ],
+            ),
+            empire: FactionConfig::new(
+                45.,
+                &[(SystemSecurity::High, 0.5), (SystemSecurity::Medium, 0.5)],
+            ),
+            federation: FactionConfig::new(
+                30.,
+                &[
+                    (SystemSecurity::Low, 0.4),
+                    (SystemSecurity::Medium, 0.4),
+                    (SystemSecurity::High, 0.2),
+                ],
+            ),
+            independent: FactionConfig::new(
+                10.,
+                &[(SystemSecurity::Anarchy, 0.5), (SystemSecurity::Low, 0.5)],
+            ),
+        }
+    }
+}
+
+/// Parameters of a single faction.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct FactionConfig {
+    /// Relative chance of a sector belonging to the faction.
+    pub weight: f64,
+    /// Chance of each security level for systems of the faction, checked in
+    /// order.
+    pub security: Vec<SecurityChance>,
+}
+
+impl FactionConfig {
+    fn new(weight: f64, security: &[(SystemSecurity, f64)]) -> Self {
+        FactionConfig {
+            weight,
+            security: security
+                .iter()
+                .map(|(level, chance)| SecurityChance {
+                    level: level.clone(),
+                    chance: *chance,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Chance of a system being assigned the given security level.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct SecurityChance {
+    pub level: SystemSecurity,
+    pub chance: f64,
+}
+
+/// Parameters for the economic agents of each system.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct EconomyConfig {
+    pub starting_credits: u64,
+    /// Initial lower bound of each agent's belief of the price of a commodity.
+    pub initial_lower_belief: i64,
+    /// Initial upper bound of each agent's belief of the price of a commodity.
+    pub initial_upper_belief: i64,
+    pub min_price: i64,
+    pub max_price: i64,
+    /// Production and consumption per unit of population.
+    pub population_factor: f64,
+}
+
+impl Default for EconomyConfig {
+    fn default() -> EconomyConfig {
+        EconomyConfig {
+            starting_credits: 100_000,
+            initial_lower_belief: 1000,
+            initial_upper_belief: 10000,
+            min_price: 10,
+            max_price: 1_000_000,
+            population_factor: 1.,
+        }
+    }
+}
+
+/// A single invalid field of a config, identified by its TOML path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Error returned for a config with one or more invalid fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidConfig(pub Vec<FieldError>);
+
+impl InvalidConfig {
+    fn single<E: fmt::Display>(path: &str, error: E) -> Self {
+        InvalidConfig(vec![FieldError {
+            path: String::from(path),
+            message: error.to_string(),
+        }])
+    }
+}
+
+impl fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid config, {} error(s):", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InvalidConfig {}
+
+/// Collects the errors of failed checks.
+#[derive(Default)]
+struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    fn check(&mut self, ok: bool, path: &str, message: &str) {
+        if !ok {
+            self.errors.push(FieldError {
+                path: String::from(path),
+                message: String::from(message),
+            });
+        }
+    }
+
+    fn positive(&mut self, value: f64, path: &str) {
+        self.check(
+            value.is_finite() && value > 0.,
+            path,
+            &format!("must be a positive number, got {}", value),
+        );
+    }
+
+    fn finite(&mut self, value: f64, path: &str) {
+        self.check(
+            value.is_finite(),
+            path,
+            &format!("must be a number, got {}", value),
+        );
+    }
+
+    fn fraction(&mut self, value: f64, path: &str) {
+        self.check(
+            (0. ..=1.).contains(&value),
+            path,
+            &format!("must be between 0 and 1, got {}", value),
+        );
+    }
+
+    fn non_negative(&mut self, value: f64, path: &str) {
+        self.check(
+            value.is_finite() && value >= 0.,
+            path,
+            &format!("must be zero or positive, got {}", value),
+        );
+    }
+}
+
+/// Returns the explanation of the field at the given TOML path, if any.
+fn field_doc(path: &str) -> Option<&'static str> {
+    let doc = match path {
+        "map_seed" => "Seed of the galaxy, the same seed and config always generate the same galaxy.",
+        "number_of_systems" => "Number of star systems in the galaxy.",
+        "system_spread" => "Standard deviation of system locations from the galactic center, in light years.",
+        "number_of_sectors" => "Number of sectors the systems are grouped in, each owned by one faction.\nMust be between 1 and number_of_systems.",
+        "min_system_distance" => "Smallest distance between any two systems, in light years. Generation\nfails if the galaxy is too dense to fit every system this far apart.",
+        "shape" => "Shape of the galaxy, distances are relative to system_spread. The kind is one of:\n  gaussian\n  elliptical, with ratio = 0.5 and rotation = 0.0\n  spiral, with arms = 2, twist = 2.0, bulge = 0.15, bulge_size = 0.3 and arm_width = 0.12\n  ring, with radius = 2.0 and width = 0.2\n  clustered, with clusters = 20 and cluster_spread = 0.15",
+        "shape.ratio" => "Ratio of the minor axis to the major axis.",
+        "shape.rotation" => "Rotation of the major axis, in degrees.",
+        "shape.arms" => "Number of spiral arms.",
+        "shape.twist" => "Angle in radians which the arms turn per system_spread of distance from the center.",
+        "shape.bulge" => "Fraction of the systems placed in the central bulge.",
+        "shape.bulge_size" => "Standard deviation of the central bulge.",
+        "shape.arm_width" => "Standard deviation of systems from the center of their arm.",
+        "shape.radius" => "Radius of the ring.",
+        "shape.width" => "Standard deviation of systems from the ring.",
+        "shape.clusters" => "Number of clusters.",
+        "shape.cluster_spread" => "Standard deviation of systems from the center of their cluster.",
+        "stars" => "Star masses, in solar masses, follow a Gamma distribution.",
+        "stars.mass_shape" => "Shape of the star mass distribution.",
+        "stars.mass_rate" => "Rate of the star mass distribution, higher values give lighter stars.",
+        "stars.min_mass" => "Smallest mass of any star.",
+        "stars.binary_chance" => "Chance of a system having two stars.",
+        "stars.trinary_chance" => "Chance of a system having three stars.",
+        "stars.min_separation" => "Smallest orbital separation of a companion star from the primary star,\nin light seconds. Planets orbit a single star when well within the\nseparation, and all stars when well outside of it.",
+        "stars.max_separation" => "Largest orbital separation of a companion star, in light seconds.",
+        "stars.giant_chance" => "Chance of a star heavy enough to have left the main sequence having\nexpanded into a giant, or a supergiant if heavier than 8 solar masses.",
+        "stars.white_dwarf_chance" => "Chance of a star being a white dwarf.",
+        "stars.neutron_star_chance" => "Chance of a star being a neutron star.",
+        "stars.black_hole_chance" => "Chance of a star being a black hole.",
+        "planets" => "Planets orbiting each star.",
+        "planets.mean_count" => "Mean number of planets in a system.",
+        "planets.min_count" => "Smallest number of planets in a system.",
+        "planets.mean_mass" => "Mean planet mass, in Earth masses.",
+        "planets.min_orbit_distance" => "Minimal distance from a planet to its star.",
+        "planets.mean_moons" => "Mean number of moons of a planet.",
+        "planets.mean_giant_moons" => "Mean number of moons of a gas giant.",
+        "planets.belt_chance" => "Chance of a system having an asteroid belt, and of each further belt.",
+        "factions" => "Each faction has a relative weight, the chance of a sector belonging to it,\nand chances of the security levels of its systems, which must sum to 1.\nSecurity levels are Anarchy, Low, Medium or High.",
+        "economy" => "Economic agents trading in every system.",
+        "economy.starting_credits" => "Credits each system starts with.",
+        "economy.initial_lower_belief" => "Initial lower bound of the price each system expects for a commodity.",
+        "economy.initial_upper_belief" => "Initial upper bound of the price each system expects for a commodity.",
+        "economy.min_price" => "Lowest price any commodity can be traded for.",
+        "economy.max_price" => "Highest price any commodity can be traded for.",
+        "economy.population_factor" => "Production and consumption of commodities per unit of population.",
+        _ => return None,
+    };
+    Some(doc)
+}
+
+/// Reports every key of the value which is missing from the reference, where
+/// arrays are checked against the first element of the reference array.
+fn unknown_fields(value: &Value, reference: &Value, path: &str, errors: &mut Vec<FieldError>) {
+    match (value, reference) {
+        (Value::Table(table), Value::Table(reference)) => {
+            for (key, value) in table {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match reference.get(key) {
+                    Some(reference) => unknown_fields(value, reference, &path, errors),
+                    None => errors.push(FieldError {
+                        path,
+                        message: String::from("unknown field"),
+                    }),
+                }
+            }
+        }
+        (Value::Array(array), Value::Array(reference)) => {
+            if let Some(reference) = reference.first() {
+                for (index, value) in array.iter().enumerate() {
+                    unknown_fields(value, reference, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config() {
+        let config: GameConfig = toml::from_str(
+            r#"
+            number_of_systems = 500
+
+            [planets]
+            mean_count = 5.0
+
+            [factions.cartel]
+            weight = 1.0
+            security = [{ level = "Anarchy", chance = 1.0 }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.number_of_systems, 500);
+        assert_eq!(config.map_seed, GameConfig::default().map_seed);
+        assert_eq!(config.planets.mean_count, 5.);
+        assert_eq!(config.planets.min_orbit_distance, 500.);
+        assert_eq!(config.factions.cartel.weight, 1.);
+        assert_eq!(config.factions.empire, FactionsConfig::default().empire);
+        assert_eq!(config.economy, EconomyConfig::default());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let errors = GameConfig::from_toml(
+            r#"
+            number_of_systems = 10
+            number_of_sectors = 20
+            system_spread = 1e-9
+            seed = 3
+
+            [stars]
+            mass_rate = -1.0
+
+            [factions.empire]
+            weight = 1.0
+            security = [{ level = "High", chance = 0.5, typo = 1 }]
+            "#,
+        )
+        .unwrap_err();
+        let paths = errors.0.iter().map(|e| e.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "factions.empire.security[0].typo",
+                "seed",
+                "system_spread",
+                "number_of_sectors",
+                "stars.mass_rate",
+                "factions.empire.security"
+            ]
+        );
+        assert!(GameConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_documented_toml() {
+        for preset in &[Preset::Small, Preset::Medium, Preset::Huge] {
+            let config = GameConfig::preset(*preset);
+            let content = config.to_documented_toml();
+            assert!(content.contains("# Number of star systems in the galaxy.\n"));
+            assert_eq!(GameConfig::from_toml(&content), Ok(config));
         }
+        assert_eq!(GameConfig::preset(Preset::Medium), GameConfig::default());
     }
 }
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use bincode;
+use rayon::ThreadPoolBuilder;
+use serde::Serialize;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use config::GameConfig;
+use economy::Economy;
+use game::Updatable;
+use generators::generate_galaxy;
+
+/// Canonical hashes of every entity in a generated galaxy and its economy,
+/// listed in a fixed order so that two runs can be compared entity by entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    pub entities: Vec<(String, u64)>,
+}
+
+impl Fingerprint {
+    /// Generates the galaxy for the config on a thread pool of the given size,
+    /// runs the economy the given number of ticks and hashes the result.
+    pub fn generate(config: &GameConfig, threads: usize, ticks: u32) -> Result<Self> {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+        pool.install(|| {
+            let galaxy = generate_galaxy(config)?;
+            let mut economy = Economy::new(&galaxy, &config.economy);
+            for _ in 0..ticks {
+                economy.update();
+            }
+
+            let mut entities = vec![];
+            for (index, sector) in galaxy.sectors.iter().enumerate() {
+                entities.push((format!("sector {}", index), hash(sector)?));
+            }
+
+            // Systems are stored in a map, order them by location.
+            let mut systems = galaxy.systems().collect::<Vec<_>>();
+            systems.sort_by(|a, b| {
+                (a.location.x, a.location.y)
+                    .partial_cmp(&(b.location.x, b.location.y))
+                    .unwrap()
+            });
+            for system in &systems {
+                let name = format!(
+                    "system {} at ({}, {})",
+                    system.name, system.location.x, system.location.y
+                );
+                entities.push((name, hash(system)?));
+            }
+            for system in &systems {
+                let market = (economy.listings(system), economy.populations(system));
+                entities.push((format!("market of {}", system.name), hash(&market)?));
+            }
+            Ok(Fingerprint { entities })
+        })
+    }
+
+    /// Returns a single hash of all entities.
+    pub fn hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.entities.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns the name of the first entity which differs between the two
+    /// fingerprints, if any.
+    pub fn first_divergence(&self, other: &Fingerprint) -> Option<String> {
+        self.entities
+            .iter()
+            .zip(&other.entities)
+            .find(|(a, b)| a != b)
+            .map(|(a, _)| a.0.clone())
+            .or_else(|| {
+                if self.entities.len() != other.entities.len() {
+                    Some(format!(
+                        "entity count, {} and {}",
+                        self.entities.len(),
+                        other.entities.len()
+                    ))
+                } else {
+                    None
+                }
+            })
+    }
+}
+
+/// Hashes the serialized form of the value, which captures every float exactly.
+fn hash<T: Serialize>(value: &T) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    bincode::serialize(value)?.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_is_deterministic() {
+        let config = GameConfig {
+            number_of_systems: 300,
+            number_of_sectors: 5,
+            ..GameConfig::default()
+        };
+        let reference = Fingerprint::generate(&config, 1, 10).unwrap();
+        for threads in &[1, 4] {
+            let fingerprint = Fingerprint::generate(&config, *threads, 10).unwrap();
+            assert_eq!(reference.first_divergence(&fingerprint), None);
+            assert_eq!(reference.hash(), fingerprint.hash());
+        }
+
+        let other = Fingerprint::generate(
+            &GameConfig {
+                map_seed: 7,
+                ..config
+            },
+            1,
+            10,
+        )
+        .unwrap();
+        assert_eq!(
+            reference.first_divergence(&other),
+            Some(reference.entities[0].0.clone())
+        );
+    }
+}
@@ -3,13 +3,14 @@ use statrs::distribution::{Continuous, DiscreteUniform, Distribution, Gamma};
 use std::{collections::HashMap, iter::repeat, ops::Range};
 
 use super::*;
+use config::EconomyConfig;
 use entities::{PlanetType, System};
-use resources::{fetch_resource, AgentResource};
+use resources::{fetch_resource, AgentResource, PlanetTypesResource};
 
 /// Economic agent, able to take part in transactions.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Agent {
-    seed: u32,
+    hash: u64,
     credits: u64,
     populations: Vec<f64>,
     inventory: HashMap<Commodity, u64>,
@@ -19,75 +20,112 @@ pub struct Agent {
 }
 
 impl Agent {
-    // TODO: Move to config?
-    const STARTING_CREDITS: u64 = 100_000;
-    const INITIAL_LOWER_BELIEF: i64 = 1000;
-    const INITIAL_UPPER_BELIEF: i64 = 10000;
-    const DEFAULT_PRICE: i64 = (Agent::INITIAL_LOWER_BELIEF + Agent::INITIAL_UPPER_BELIEF) / 2;
-    const MIN_PRICE: i64 = 10;
-    const POPULATION_FACTOR: f64 = 1.;
+    /// Population of the miners of an asteroid belt.
+    const BELT_POPULATION: f64 = 10.;
+    /// Population of a station.
+    const STATION_POPULATION: f64 = 100.;
 
     /// Create a new economic agent for the given system.
-    pub fn new(system: &System) -> Self {
+    pub fn new(system: &System, config: &EconomyConfig) -> Self {
         let resource = fetch_resource::<AgentResource>().unwrap();
+        let types = fetch_resource::<PlanetTypesResource>().unwrap().types;
+        let type_population = |kind: &PlanetType| {
+            types
+                .iter()
+                .find(|info| info.name == kind.0)
+                .map_or(0., |info| info.population)
+        };
+
+        // Every planet, moon, belt and station consumes and produces
+        // according to its economy.
+        let bodies = system
+            .satelites
+            .iter()
+            .flat_map(|planet| {
+                Some((
+                    &planet.economic_type,
+                    Agent::initial_population(
+                        planet.mass,
+                        type_population(&planet.planet_type),
+                        planet.habitability,
+                    ),
+                ))
+                .into_iter()
+                .chain(planet.moons.iter().map(|moon| {
+                    (
+                        &moon.economic_type,
+                        Agent::initial_population(
+                            moon.mass,
+                            type_population(&moon.planet_type),
+                            moon.habitability,
+                        ),
+                    )
+                }))
+            })
+            .chain(
+                system
+                    .belts
+                    .iter()
+                    .map(|belt| (&belt.economic_type, Agent::BELT_POPULATION)),
+            )
+            .chain(
+                system
+                    .stations
+                    .iter()
+                    .map(|station| (&station.economic_type, Agent::STATION_POPULATION)),
+            )
+            .collect::<Vec<_>>();
 
         // Setup ideal consumption.
-        let ideals = system.satelites.iter().fold(vec![], |mut ideals, planet| {
-            ideals.push(
+        let ideals = bodies
+            .iter()
+            .map(|(economy, _)| {
                 Commodity::values()
                     .map(|commodity| {
-                        let planet_ideal = *resource.faction_ideals[&system.faction]
+                        let ideal = *resource.faction_ideals[&system.faction]
                             .get(commodity)
                             .unwrap_or(&0)
-                            + *resource.planet_ideals[&planet.economic_type]
+                            + *resource.planet_ideals[*economy]
                                 .get(commodity)
                                 .unwrap_or(&0);
-                        (commodity.clone(), planet_ideal)
+                        (commodity.clone(), ideal)
                     })
-                    .collect(),
-            );
-            ideals
-        });
+                    .collect()
+            })
+            .collect();
 
         // Setup system commodity production.
-        let productions = system
-            .satelites
+        let productions = bodies
             .iter()
-            .fold(vec![], |mut productions, planet| {
-                productions.push(
-                    Commodity::values()
-                        .map(|commodity| {
-                            let planet_production = *resource.faction_production[&system.faction]
+            .map(|(economy, _)| {
+                Commodity::values()
+                    .map(|commodity| {
+                        let production = *resource.faction_production[&system.faction]
+                            .get(commodity)
+                            .unwrap_or(&0)
+                            + *resource.planet_production[*economy]
                                 .get(commodity)
-                                .unwrap_or(&0)
-                                + *resource.planet_production[&planet.economic_type]
-                                    .get(commodity)
-                                    .unwrap_or(&0);
-                            (commodity.clone(), planet_production)
-                        })
-                        .collect(),
-                );
-                productions
-            });
+                                .unwrap_or(&0);
+                        (commodity.clone(), production)
+                    })
+                    .collect()
+            })
+            .collect();
 
         // Create initial price beliefs.
         let price_beliefs = Commodity::values()
             .map(|commodity| {
                 (
                     commodity.clone(),
-                    Agent::INITIAL_LOWER_BELIEF..Agent::INITIAL_UPPER_BELIEF,
+                    config.initial_lower_belief..config.initial_upper_belief,
                 )
             })
             .collect();
 
         Agent {
-            seed: system.location.hash() as u32,
-            populations: system
-                .satelites
-                .iter()
-                .map(|planet| Agent::initial_population(planet.mass, &planet.planet_type))
-                .collect(),
-            credits: Agent::STARTING_CREDITS,
+            hash: system.location.hash(),
+            populations: bodies.iter().map(|(_, population)| *population).collect(),
+            credits: config.starting_credits,
             inventory: HashMap::new(),
             ideals,
             productions,
@@ -95,23 +133,24 @@ impl Agent {
         }
     }
 
-    /// Calculates the initial planet population based on mass and planet type.
-    fn initial_population(mass: f64, kind: &PlanetType) -> f64 {
+    /// Calculates the initial planet population based on mass, the population
+    /// of its planet type and habitability. Habitable planets are settled the
+    /// most, while the type adds colonies such as mines on planets rich in
+    /// metals.
+    fn initial_population(mass: f64, type_factor: f64, habitability: f64) -> f64 {
         let mass_factor = Gamma::new(7., 5.).unwrap();
-        let type_factor: f64 = match *kind {
-            PlanetType::Metal => 150.,
-            PlanetType::Earth => 800.0,
-            PlanetType::Rocky => 1.,
-            PlanetType::Icy => 0.5,
-            PlanetType::GasGiant => 0.,
-        };
-        mass_factor.pdf(mass) * type_factor * 100.
+        mass_factor.pdf(mass) * (type_factor + 1_000. * habitability) * 100.
+    }
+
+    /// Returns the price of commodities for which there is no belief yet.
+    fn default_price(config: &EconomyConfig) -> i64 {
+        (config.initial_lower_belief + config.initial_upper_belief) / 2
     }
 
     /// Returns the hash of the system to which the agent is associated.
     #[allow(dead_code)]
-    pub fn hash(&self) -> u32 {
-        self.seed
+    pub fn hash(&self) -> u64 {
+        self.hash
     }
 
     /// Returns the system populations.
@@ -122,11 +161,11 @@ impl Agent {
 
     /// Returns the prices for all commodities known.
     #[allow(dead_code)]
-    pub fn prices(&self) -> Vec<(Commodity, i64)> {
+    pub fn prices(&self, config: &EconomyConfig) -> Vec<(Commodity, i64)> {
         Commodity::values().fold(vec![], |mut prices, commodity| {
             if let Some(range) = self.price_beliefs.get(commodity) {
                 let price = (range.start + range.end) / 2;
-                if price != Agent::DEFAULT_PRICE {
+                if price != Agent::default_price(config) {
                     prices.push((commodity.clone(), (range.start + range.end) / 2));
                 }
             }
@@ -135,8 +174,47 @@ impl Agent {
         })
     }
 
+    /// Returns the current price for the given commodity, i.e the mean of the
+    /// price belief.
+    pub fn price(&self, commodity: &Commodity, config: &EconomyConfig) -> u64 {
+        self.price_beliefs
+            .get(commodity)
+            .map_or(Agent::default_price(config), |range| {
+                (range.start + range.end) / 2
+            }) as u64
+    }
+
+    /// Returns the price and stock of every commodity.
+    pub fn listings(&self, config: &EconomyConfig) -> Vec<(Commodity, u64, u64)> {
+        Commodity::values()
+            .map(|commodity| {
+                (
+                    commodity.clone(),
+                    self.price(commodity, config),
+                    self.stock(commodity),
+                )
+            })
+            .collect()
+    }
+
+    /// Sets the price belief for the given commodity.
+    #[cfg(test)]
+    pub fn set_price_belief(&mut self, commodity: &Commodity, belief: Range<i64>) {
+        self.price_beliefs.insert(commodity.clone(), belief);
+    }
+
+    /// Returns the amount of the given commodity in the inventory.
+    pub fn stock(&self, commodity: &Commodity) -> u64 {
+        *self.inventory.get(commodity).unwrap_or(&0)
+    }
+
+    /// Returns the amount of credits held by the agent.
+    pub fn credits(&self) -> u64 {
+        self.credits
+    }
+
     /// Returns the balance of a given commodity compared to the ideal amount currently in inventory.
-    fn balance(&self, commodity: &Commodity) -> i64 {
+    fn balance(&self, commodity: &Commodity, config: &EconomyConfig) -> i64 {
         let current_stock = self.inventory.get(commodity).unwrap_or(&0);
         let ideal_stock = self
             .ideals
@@ -144,10 +222,10 @@ impl Agent {
             .enumerate()
             .fold(0, |acc, (index, ideal)| {
                 acc + (self.populations[index]
-                    * Agent::POPULATION_FACTOR
+                    * config.population_factor
                     * *ideal.get(commodity).unwrap_or(&0) as f64) as i64
             });
-        (*current_stock as i64) - (ideal_stock as i64)
+        (*current_stock as i64) - ideal_stock
     }
 
     /// Adds delta amount of the given commodity to the inventory.
@@ -158,18 +236,24 @@ impl Agent {
 
     /// Adds delta amount of credits.
     pub fn update_credits(&mut self, delta: i64) {
-        self.credits = (self.credits as i64 + delta as i64) as u64;
+        self.credits = (self.credits as i64 + delta) as u64;
     }
 
     /// Updates the price beliefs for the given commodity based on the given unit price.
     /// Success indicates whether the agent was successful in trading.
     /// Successful trades will strengthen the belief whereas unsucessful trades will weaken the belief.
-    pub fn update_price_belief(&mut self, commodity: &Commodity, unit_price: u64, success: bool) {
+    pub fn update_price_belief(
+        &mut self,
+        commodity: &Commodity,
+        unit_price: u64,
+        success: bool,
+        config: &EconomyConfig,
+    ) {
         let price_belief = self.price_beliefs.get_mut(commodity).unwrap();
         let curr_mean = (price_belief.start + price_belief.end) / 2;
 
         // Translate mean towards unit_price by 10%.
-        let new_mean = curr_mean + ((unit_price as f64 - curr_mean as f64) * 1.1) as i64;
+        let new_mean = curr_mean + ((unit_price as f64 - curr_mean as f64) * 0.1) as i64;
 
         price_belief.end += new_mean - curr_mean;
         price_belief.start -= curr_mean - new_mean;
@@ -182,21 +266,27 @@ impl Agent {
             price_belief.end = (price_belief.end as f64 * 1.05) as i64;
         }
 
-        // Ensure reasonable price beliefs.
+        // Ensure reasonable price beliefs, keeping the start below the end so
+        // that the end never has to be raised above the highest price.
+        price_belief.end = price_belief.end.min(config.max_price);
         price_belief.start = price_belief
             .start
-            .max(Agent::MIN_PRICE)
-            .min(price_belief.end);
+            .max(config.min_price)
+            .min(price_belief.end - 1);
         price_belief.end = price_belief.end.max(price_belief.start + 1);
     }
 
     /// Generate a bid for the given commodity if there exists a demand.
-    pub fn generate_bid(&self, commodity: &Commodity) -> Option<BidBuilder> {
-        let balance = self.balance(commodity);
+    pub fn generate_bid(
+        &self,
+        commodity: &Commodity,
+        config: &EconomyConfig,
+    ) -> Option<BidBuilder> {
+        let balance = self.balance(commodity, config);
 
         // Place bid if we have demand.
         if balance < 0 {
-            let seed: &[u32] = &[self.seed];
+            let seed: &[u32] = &[self.hash as u32];
             let mut rng: ChaChaRng = SeedableRng::from_seed(seed);
 
             let price_belief = &self.price_beliefs[commodity];
@@ -214,12 +304,16 @@ impl Agent {
     }
 
     /// Generate an ask for the given commodity if there exists surplus.
-    pub fn generate_ask(&self, commodity: &Commodity) -> Option<AskBuilder> {
-        let balance = self.balance(commodity);
+    pub fn generate_ask(
+        &self,
+        commodity: &Commodity,
+        config: &EconomyConfig,
+    ) -> Option<AskBuilder> {
+        let balance = self.balance(commodity, config);
 
         // Place ask if we have excess.
         if balance > 0 {
-            let seed: &[u32] = &[self.seed];
+            let seed: &[u32] = &[self.hash as u32];
             let mut rng: ChaChaRng = SeedableRng::from_seed(seed);
 
             let price_belief = &self.price_beliefs[commodity];
@@ -239,16 +333,8 @@ impl Agent {
     /// Updates the planet populations held by the agent based on potential economic growth.
     pub fn update_population(&mut self, demand_supply: &[(Commodity, u64, u64)]) {
         for (index, population) in self.populations.iter_mut().enumerate() {
-            let productions = &self.productions[index];
             let potential_earnings =
-                demand_supply
-                    .iter()
-                    .fold(0, |acc, (commodity, demand, supply)| {
-                        match productions.get(commodity) {
-                            Some(0) | None => acc,
-                            Some(prod) => acc + ((demand - supply) as i64) / (*prod as i64),
-                        }
-                    });
+                Agent::potential_earnings(&self.productions[index], demand_supply);
             *population += match potential_earnings.signum() {
                 1 => (*population * 0.1).min(10.),
                 -1 => (*population * -0.1).max(-10.).max(-*population),
@@ -256,11 +342,26 @@ impl Agent {
             };
         }
     }
-}
 
-impl Updatable for Agent {
+    /// Returns the excess demand for the commodities produced, relative to the
+    /// production. Negative when the market is oversupplied, the difference
+    /// is signed since supply may exceed demand.
+    fn potential_earnings(
+        productions: &HashMap<Commodity, u64>,
+        demand_supply: &[(Commodity, u64, u64)],
+    ) -> i64 {
+        demand_supply
+            .iter()
+            .fold(0, |acc, (commodity, demand, supply)| {
+                match productions.get(commodity) {
+                    Some(0) | None => acc,
+                    Some(prod) => acc + (*demand as i64 - *supply as i64) / (*prod as i64),
+                }
+            })
+    }
+
     /// Updates the inventory based on the consumption and production.
-    fn update(&mut self) {
+    pub fn update(&mut self, config: &EconomyConfig) {
         for (index, (commodity, amount)) in self
             .productions
             .clone()
@@ -271,7 +372,7 @@ impl Updatable for Agent {
             let population = self.populations[index];
             self.update_inventory(
                 commodity,
-                (*amount as f64 * population * Agent::POPULATION_FACTOR) as i64,
+                (*amount as f64 * population * config.population_factor) as i64,
             );
         }
         for (index, (commodity, amount)) in self
@@ -284,8 +385,53 @@ impl Updatable for Agent {
             let population = self.populations[index];
             self.update_inventory(
                 commodity,
-                -(*amount as f64 * population * Agent::POPULATION_FACTOR) as i64,
+                -(*amount as f64 * population * config.population_factor) as i64,
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_price_belief() {
+        let config = EconomyConfig::default();
+        let food = Commodity::Food;
+        let mut agent = Agent {
+            hash: 0,
+            credits: 0,
+            populations: vec![],
+            inventory: HashMap::new(),
+            ideals: vec![],
+            productions: vec![],
+            price_beliefs: vec![(food.clone(), 1_000..10_000)].into_iter().collect(),
+        };
+
+        // The belief moves towards the traded price without passing it.
+        agent.update_price_belief(&food, 1_500, true, &config);
+        let belief = agent.price_beliefs[&food].clone();
+        let mean = (belief.start + belief.end) / 2;
+        assert!(mean > 1_500 && mean < 5_500, "{:?}", belief);
+
+        // Beliefs stay within the allowed prices.
+        for _ in 0..100 {
+            agent.update_price_belief(&food, 10 * config.max_price as u64, false, &config);
+        }
+        let belief = agent.price_beliefs[&food].clone();
+        assert_eq!(belief.end, config.max_price);
+        assert!(belief.start < belief.end);
+    }
+
+    #[test]
+    fn test_oversupply_shrinks_population() {
+        // Subtracting the supply from the demand as unsigned numbers used to
+        // overflow, panicking in debug builds and growing the population in
+        // release builds.
+        let food = Commodity::Food;
+        let productions = vec![(food.clone(), 1)].into_iter().collect();
+        let demand_supply = [(food, 0, 10)];
+        assert_eq!(Agent::potential_earnings(&productions, &demand_supply), -10);
+    }
+}
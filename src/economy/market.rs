@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::{cmp::Reverse, collections::HashMap};
 
+use config::EconomyConfig;
 use entities::System;
 
 use super::*;
@@ -26,29 +27,41 @@ impl Market {
 
     /// Returns the agent, if any, which is associated with the given system.
     #[allow(dead_code)]
-    pub fn agent(&self, system_hash: u32) -> Option<&Arc<Mutex<Agent>>> {
+    pub fn agent(&self, system_hash: u64) -> Option<&Arc<Mutex<Agent>>> {
         self.agents
             .iter()
             .find(|agent| agent.lock().unwrap().hash() == system_hash)
     }
 
     /// Returns a reference to all agents.
-    #[allow(dead_code)]
     pub fn agents(&self) -> &Vec<Arc<Mutex<Agent>>> {
         &self.agents
     }
 
     /// Adds the given system to this market.
-    pub fn add_system(&mut self, system: &System) {
-        self.agents.push(Arc::new(Mutex::new(Agent::new(system))));
+    pub fn add_system(&mut self, system: &System, config: &EconomyConfig) {
+        self.agents
+            .push(Arc::new(Mutex::new(Agent::new(system, config))));
+    }
+
+    /// Orders the bids by increasing and the asks by decreasing unit price,
+    /// regardless of the amounts offered.
+    fn sort_offers(bids: &mut [Bid], asks: &mut [Ask]) {
+        bids.sort_unstable_by_key(|bid| bid.unit_price);
+        asks.sort_unstable_by_key(|ask| Reverse(ask.unit_price));
     }
 
     /// Attemps to resolve the bids and asks for the given commodity by matching
     /// the highest asks with the lowest bids performing the transaction.
     /// Returns the quantity traded.
-    fn resolve_offers(&mut self, commodity: &Commodity, mut bids: Vec<Bid>, mut asks: Vec<Ask>) {
-        bids.sort_unstable_by(|a, b| a.amount.partial_cmp(&b.unit_price).unwrap());
-        asks.sort_unstable_by(|a, b| b.amount.partial_cmp(&a.unit_price).unwrap());
+    fn resolve_offers(
+        &mut self,
+        commodity: &Commodity,
+        mut bids: Vec<Bid>,
+        mut asks: Vec<Ask>,
+        config: &EconomyConfig,
+    ) {
+        Market::sort_offers(&mut bids, &mut asks);
 
         let mut money_traded = 0;
         let mut amount_traded = 0;
@@ -81,8 +94,8 @@ impl Market {
                 seller.update_inventory(commodity, -(quantity_traded as i64));
 
                 // Update agent price beliefs on success
-                buyer.update_price_belief(commodity, clearing_price, true);
-                seller.update_price_belief(commodity, clearing_price, true);
+                buyer.update_price_belief(commodity, clearing_price, true, config);
+                seller.update_price_belief(commodity, clearing_price, true, config);
             }
             // Remove bid or ask if the seller/buyer is out of need or stock
             if ask.amount > 0 {
@@ -95,36 +108,38 @@ impl Market {
         }
 
         // Use previous average if no trades were made.
-        if amount_traded > 0 {
+        if let Some(new_average) = money_traded.checked_div(amount_traded) {
             let average_price = self.average_prices.get_mut(commodity).unwrap();
-            *average_price = money_traded / amount_traded;
+            *average_price = new_average;
         }
 
         let average_price = self.average_prices[commodity];
 
         // Update price beliefs for unsuccessful bids/asks.
         for bid in bids {
-            bid.agent
-                .lock()
-                .unwrap()
-                .update_price_belief(&bid.commodity, average_price, false);
+            bid.agent.lock().unwrap().update_price_belief(
+                &bid.commodity,
+                average_price,
+                false,
+                config,
+            );
         }
         for ask in asks {
-            ask.agent
-                .lock()
-                .unwrap()
-                .update_price_belief(&ask.commodity, average_price, false);
+            ask.agent.lock().unwrap().update_price_belief(
+                &ask.commodity,
+                average_price,
+                false,
+                config,
+            );
         }
     }
-}
 
-impl Updatable for Market {
     /// Update all agents in this market generate and solve transactions to update
     /// prices for commodities.
-    fn update(&mut self) {
+    pub fn update(&mut self, config: &EconomyConfig) {
         // Make agents generate items for this simulation round.
         for agent in &self.agents {
-            agent.lock().unwrap().update();
+            agent.lock().unwrap().update(config);
         }
 
         let mut supply = HashMap::new();
@@ -134,13 +149,15 @@ impl Updatable for Market {
         for commodity in Commodity::values() {
             // Gather bids/asks from agents.
             let bids = self.agents.iter().fold(vec![], |mut bids, agent| {
-                if let Some(mut partial_bid) = agent.lock().unwrap().generate_bid(commodity) {
+                if let Some(mut partial_bid) = agent.lock().unwrap().generate_bid(commodity, config)
+                {
                     bids.push(partial_bid.agent(agent.clone()).build().unwrap());
                 }
                 bids
             });
             let asks = self.agents.iter().fold(vec![], |mut asks, agent| {
-                if let Some(mut partial_ask) = agent.lock().unwrap().generate_ask(commodity) {
+                if let Some(mut partial_ask) = agent.lock().unwrap().generate_ask(commodity, config)
+                {
                     asks.push(partial_ask.agent(agent.clone()).build().unwrap());
                 }
                 asks
@@ -158,7 +175,7 @@ impl Updatable for Market {
             });
 
             // If we managed to resolve any offers, we need to simulate one more round.
-            self.resolve_offers(commodity, bids, asks);
+            self.resolve_offers(commodity, bids, asks, config);
         }
 
         // Update supply and demand.
@@ -166,8 +183,8 @@ impl Updatable for Market {
             .map(|commodity| {
                 (
                     commodity.clone(),
-                    *demand.get(commodity).unwrap_or(&0) as u64,
-                    *supply.get(commodity).unwrap_or(&0) as u64,
+                    *demand.get(commodity).unwrap_or(&0),
+                    *supply.get(commodity).unwrap_or(&0),
                 )
             })
             .collect::<Vec<(Commodity, u64, u64)>>();
@@ -177,3 +194,42 @@ impl Updatable for Market {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::Point;
+
+    #[test]
+    fn test_sort_offers() {
+        // Offers used to be compared amount against price, which misordered
+        // them whenever a large offer came with a low price.
+        let system = System::empty_at(Point::new(0., 0.));
+        let agent = Arc::new(Mutex::new(Agent::new(&system, &EconomyConfig::default())));
+        let offers = [(1, 300), (1_000, 100), (10, 200)];
+        let mut bids = offers
+            .iter()
+            .map(|(amount, unit_price)| Bid {
+                agent: agent.clone(),
+                commodity: Commodity::Food,
+                amount: *amount,
+                unit_price: *unit_price,
+            })
+            .collect::<Vec<_>>();
+        let mut asks = offers
+            .iter()
+            .map(|(amount, unit_price)| Ask {
+                agent: agent.clone(),
+                commodity: Commodity::Food,
+                amount: *amount,
+                unit_price: *unit_price,
+            })
+            .collect::<Vec<_>>();
+        Market::sort_offers(&mut bids, &mut asks);
+
+        let bid_prices = bids.iter().map(|bid| bid.unit_price).collect::<Vec<_>>();
+        let ask_prices = asks.iter().map(|ask| ask.unit_price).collect::<Vec<_>>();
+        assert_eq!(bid_prices, vec![100, 200, 300]);
+        assert_eq!(ask_prices, vec![300, 200, 100]);
+    }
+}
@@ -1,28 +1,37 @@
 use rayon::prelude::*;
 use std::{
+    collections::HashMap,
+    error::Error,
     fmt,
     slice::Iter,
+    str::FromStr,
     sync::{Arc, Mutex},
 };
 
+use config::EconomyConfig;
 use entities::{Galaxy, System};
 use game::Updatable;
+use player::Player;
 
 mod agent;
 mod market;
+mod trading;
 
 use self::agent::Agent;
 use self::market::Market;
+pub use self::trading::TradeRun;
 
 /// Holds the economic state for the entire game.
 #[derive(Default, Serialize, Deserialize)]
 pub struct Economy {
+    config: EconomyConfig,
     markets: Vec<Market>,
 }
 
 impl Economy {
-    /// Creates the game economy using the given galaxy.
-    pub fn new(galaxy: &Galaxy) -> Economy {
+    /// Creates the game economy using the given galaxy, with agents set up
+    /// according to the config.
+    pub fn new(galaxy: &Galaxy, config: &EconomyConfig) -> Economy {
         // Create one market per sector.
         let mut markets = vec![];
         for sector in &galaxy.sectors {
@@ -32,51 +41,181 @@ impl Economy {
                 .iter()
                 .map(|loc| galaxy.system(loc).unwrap())
             {
-                market.add_system(system);
+                market.add_system(system, config);
             }
             markets.push(market);
         }
 
-        Economy { markets }
+        Economy {
+            config: *config,
+            markets,
+        }
+    }
+
+    /// Returns the agent, if any, which is associated with the given system.
+    fn agent(&self, system: &System) -> Option<&Arc<Mutex<Agent>>> {
+        let system_hash = system.location.hash();
+        self.markets
+            .iter()
+            .filter_map(|market| market.agent(system_hash))
+            .next()
     }
 
     /// Returns the prices for the available commodities the the given system.
-    #[allow(dead_code)]
     pub fn commodity_prices(&self, system: &System) -> Vec<(Commodity, i64)> {
-        let mut prices = vec![];
+        self.agent(system)
+            .map_or(vec![], |agent| agent.lock().unwrap().prices(&self.config))
+    }
 
-        let system_hash = system.location.hash();
-        for market in &self.markets {
-            if let Some(agent) = market.agent(system_hash as u32) {
-                prices = agent.lock().unwrap().prices();
-                break;
-            }
-        }
+    /// Returns the price and stock of every commodity in the given system.
+    pub fn listings(&self, system: &System) -> Vec<(Commodity, u64, u64)> {
+        self.agent(system)
+            .map_or(vec![], |agent| agent.lock().unwrap().listings(&self.config))
+    }
 
-        prices
+    /// Returns the price and stock of every commodity in all systems with a
+    /// market, keyed by the hash of the system location.
+    pub fn all_listings(&self) -> HashMap<u64, Vec<(Commodity, u64, u64)>> {
+        self.markets
+            .iter()
+            .flat_map(|market| market.agents())
+            .map(|agent| {
+                let agent = agent.lock().unwrap();
+                (agent.hash(), agent.listings(&self.config))
+            })
+            .collect()
     }
 
     #[allow(dead_code)]
     pub fn populations(&self, system: &System) -> Vec<f64> {
-        let mut populations = vec![];
+        self.agent(system)
+            .map_or(vec![], |agent| agent.lock().unwrap().populations())
+    }
 
-        let system_hash = system.location.hash();
-        for market in &self.markets {
-            if let Some(agent) = market.agent(system_hash as u32) {
-                populations = agent.lock().unwrap().populations();
-                break;
-            }
+    /// Buys the given amount of a commodity from the market in the given
+    /// system, which the player must currently be in.
+    pub fn buy(
+        &self,
+        player: &mut Player,
+        system: &System,
+        commodity: &Commodity,
+        amount: u64,
+    ) -> Result<Trade, TradeError> {
+        if amount == 0 {
+            return Err(TradeError::ZeroAmount);
         }
+        if player.location() != system.location {
+            return Err(TradeError::NotInSystem);
+        }
+        let mut agent = self
+            .agent(system)
+            .ok_or(TradeError::NoMarket)?
+            .lock()
+            .unwrap();
 
-        populations
+        if agent.stock(commodity) < amount {
+            return Err(TradeError::InsufficientStock {
+                available: agent.stock(commodity),
+            });
+        }
+        let unit_price = agent.price(commodity, &self.config);
+        let total = trade_total(unit_price, amount)?;
+        if player.credits() < total {
+            return Err(TradeError::InsufficientCredits {
+                required: total,
+                available: player.credits(),
+            });
+        }
+        if player.ship().cargo_space() < amount {
+            return Err(TradeError::InsufficientCargoSpace {
+                required: amount,
+                available: player.ship().cargo_space(),
+            });
+        }
+
+        // Transfer money and goods.
+        player.update_credits(-(total as i64));
+        agent.update_credits(total as i64);
+        player.ship_mut().update_cargo(commodity, amount as i64);
+        agent.update_inventory(commodity, -(amount as i64));
+
+        agent.update_price_belief(commodity, unit_price, true, &self.config);
+
+        Ok(Trade {
+            commodity: commodity.clone(),
+            amount,
+            unit_price,
+            total,
+        })
+    }
+
+    /// Sells the given amount of a commodity to the market in the given
+    /// system, which the player must currently be in.
+    pub fn sell(
+        &self,
+        player: &mut Player,
+        system: &System,
+        commodity: &Commodity,
+        amount: u64,
+    ) -> Result<Trade, TradeError> {
+        if amount == 0 {
+            return Err(TradeError::ZeroAmount);
+        }
+        if player.location() != system.location {
+            return Err(TradeError::NotInSystem);
+        }
+        let mut agent = self
+            .agent(system)
+            .ok_or(TradeError::NoMarket)?
+            .lock()
+            .unwrap();
+
+        if player.ship().cargo(commodity) < amount {
+            return Err(TradeError::InsufficientCargo {
+                available: player.ship().cargo(commodity),
+            });
+        }
+        let unit_price = agent.price(commodity, &self.config);
+        let total = trade_total(unit_price, amount)?;
+        if agent.credits() < total {
+            return Err(TradeError::MarketInsufficientCredits {
+                required: total,
+                available: agent.credits(),
+            });
+        }
+
+        // Transfer money and goods.
+        player.update_credits(total as i64);
+        agent.update_credits(-(total as i64));
+        player.ship_mut().update_cargo(commodity, -(amount as i64));
+        agent.update_inventory(commodity, amount as i64);
+
+        agent.update_price_belief(commodity, unit_price, true, &self.config);
+
+        Ok(Trade {
+            commodity: commodity.clone(),
+            amount,
+            unit_price,
+            total,
+        })
     }
 }
 
+/// Returns the total price of a trade, which must fit in the signed credit
+/// updates.
+fn trade_total(unit_price: u64, amount: u64) -> Result<u64, TradeError> {
+    unit_price
+        .checked_mul(amount)
+        .filter(|total| *total <= i64::MAX as u64)
+        .ok_or(TradeError::AmountTooLarge { amount })
+}
+
 impl Updatable for Economy {
     /// Advances time and updates all agents etc.
     fn update(&mut self) {
+        let config = &self.config;
         self.markets.par_iter_mut().for_each(|market| {
-            market.update();
+            market.update(config);
         });
     }
 }
@@ -99,6 +238,74 @@ pub struct Ask {
     pub unit_price: u64,
 }
 
+/// A completed trade between the player and a market.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub commodity: Commodity,
+    pub amount: u64,
+    pub unit_price: u64,
+    pub total: u64,
+}
+
+/// Reasons for which a trade between the player and a market can fail.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TradeError {
+    NoMarket,
+    NotInSystem,
+    InsufficientStock { available: u64 },
+    InsufficientCredits { required: u64, available: u64 },
+    InsufficientCargoSpace { required: u64, available: u64 },
+    InsufficientCargo { available: u64 },
+    MarketInsufficientCredits { required: u64, available: u64 },
+    AmountTooLarge { amount: u64 },
+    ZeroAmount,
+}
+
+impl fmt::Display for TradeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TradeError::NoMarket => write!(f, "There is no market in this system"),
+            TradeError::NotInSystem => write!(f, "The player is not in this system"),
+            TradeError::InsufficientStock { available } => {
+                write!(f, "Insufficient stock, only {} available", available)
+            }
+            TradeError::InsufficientCredits {
+                required,
+                available,
+            } => write!(
+                f,
+                "Insufficient credits, {} required but only {} available",
+                required, available
+            ),
+            TradeError::InsufficientCargoSpace {
+                required,
+                available,
+            } => write!(
+                f,
+                "Insufficient cargo space, {} required but only {} available",
+                required, available
+            ),
+            TradeError::InsufficientCargo { available } => {
+                write!(f, "Insufficient cargo, only {} in hold", available)
+            }
+            TradeError::MarketInsufficientCredits {
+                required,
+                available,
+            } => write!(
+                f,
+                "The market cannot afford {} credits, only {} available",
+                required, available
+            ),
+            TradeError::AmountTooLarge { amount } => {
+                write!(f, "Cannot trade {} units at once", amount)
+            }
+            TradeError::ZeroAmount => write!(f, "Cannot trade zero units"),
+        }
+    }
+}
+
+impl Error for TradeError {}
+
 /// A tradable and possibly producable commodity
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Commodity {
@@ -144,6 +351,22 @@ impl Commodity {
     }
 }
 
+impl FromStr for Commodity {
+    type Err = String;
+
+    /// Parses a commodity from either its name or its display name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_lowercase().replace(' ', "");
+        Commodity::values()
+            .find(|commodity| {
+                format!("{:?}", commodity).to_lowercase() == name
+                    || commodity.to_string().to_lowercase().replace(' ', "") == name
+            })
+            .cloned()
+            .ok_or_else(|| format!("Unknown commodity: {}", s))
+    }
+}
+
 impl fmt::Display for Commodity {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -170,3 +393,118 @@ impl fmt::Display for Commodity {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::GameConfig;
+    use generators::generate_galaxy;
+
+    #[test]
+    fn test_buy_and_sell() {
+        let config = GameConfig {
+            number_of_systems: 100,
+            number_of_sectors: 2,
+            ..GameConfig::default()
+        };
+        let galaxy = generate_galaxy(&config).unwrap();
+        let economy = Economy::new(&galaxy, &config.economy);
+        let system = galaxy.systems().next().unwrap();
+        let mut player = Player::new(system.location);
+        let food = Commodity::Food;
+
+        assert_eq!(
+            economy.sell(&mut player, system, &food, 1),
+            Err(TradeError::InsufficientCargo { available: 0 })
+        );
+        assert_eq!(
+            economy.buy(&mut player, system, &food, 1),
+            Err(TradeError::InsufficientStock { available: 0 })
+        );
+
+        economy
+            .agent(system)
+            .unwrap()
+            .lock()
+            .unwrap()
+            .update_inventory(&food, 100);
+        let credits = player.credits();
+        let trade = economy.buy(&mut player, system, &food, 5).unwrap();
+        assert_eq!(player.credits(), credits - trade.total);
+        assert_eq!(player.ship().cargo(&food), 5);
+        assert_eq!(
+            economy.buy(&mut player, system, &food, 100),
+            Err(TradeError::InsufficientStock { available: 95 })
+        );
+
+        let trade = economy.sell(&mut player, system, &food, 5).unwrap();
+        assert_eq!(trade.amount, 5);
+        assert_eq!(player.ship().cargo_used(), 0);
+        assert_eq!(
+            economy.agent(system).unwrap().lock().unwrap().stock(&food),
+            100
+        );
+    }
+
+    #[test]
+    fn test_trade_limits() {
+        let config = GameConfig {
+            number_of_systems: 100,
+            number_of_sectors: 2,
+            ..GameConfig::default()
+        };
+        let galaxy = generate_galaxy(&config).unwrap();
+        let economy = Economy::new(&galaxy, &config.economy);
+        let system = galaxy.systems().next().unwrap();
+        let mut player = Player::new(system.location);
+        let food = Commodity::Food;
+        let agent = economy.agent(system).unwrap();
+
+        // Empty trades are rejected without affecting the price.
+        agent.lock().unwrap().update_inventory(&food, 100);
+        let unit_price = agent.lock().unwrap().price(&food, &economy.config);
+        assert_eq!(
+            economy.buy(&mut player, system, &food, 0),
+            Err(TradeError::ZeroAmount)
+        );
+        assert_eq!(
+            economy.sell(&mut player, system, &food, 0),
+            Err(TradeError::ZeroAmount)
+        );
+        assert_eq!(
+            agent.lock().unwrap().price(&food, &economy.config),
+            unit_price
+        );
+
+        assert_eq!(
+            economy.buy(&mut player, system, &food, u64::MAX),
+            Err(TradeError::InsufficientStock { available: 100 })
+        );
+        assert_eq!(
+            economy.sell(&mut player, system, &food, u64::MAX),
+            Err(TradeError::InsufficientCargo { available: 0 })
+        );
+
+        // Enough stock for an amount whose total overflows.
+        agent
+            .lock()
+            .unwrap()
+            .update_inventory(&food, i64::MAX - 100);
+        let amount = i64::MAX as u64;
+        assert_eq!(
+            economy.buy(&mut player, system, &food, amount),
+            Err(TradeError::AmountTooLarge { amount })
+        );
+
+        let unit_price = agent.lock().unwrap().price(&food, &economy.config);
+        let amount = player.credits() / unit_price + 1;
+        assert_eq!(
+            economy.buy(&mut player, system, &food, amount),
+            Err(TradeError::InsufficientCredits {
+                required: unit_price * amount,
+                available: player.credits(),
+            })
+        );
+        assert_eq!(player.ship().cargo_used(), 0);
+    }
+}
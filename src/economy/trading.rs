@@ -0,0 +1,171 @@
+use std::cmp::Ordering;
+
+use super::*;
+use utils::Point;
+
+/// A commodity run, buying at the origin and selling at the destination.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TradeRun {
+    pub commodity: Commodity,
+    pub destination: Point,
+    pub jumps: usize,
+    pub amount: u64,
+    pub buy_price: u64,
+    pub sell_price: u64,
+    pub profit: u64,
+    pub profit_per_jump: f64,
+}
+
+impl Economy {
+    /// Finds the most profitable commodity runs starting at the origin system
+    /// for a ship with the given cargo capacity and jump range, buying no more
+    /// than the credits allow and considering destinations at most max_jumps
+    /// away. Runs are ranked by profit per jump.
+    pub fn best_trades(
+        &self,
+        galaxy: &Galaxy,
+        origin: &Point,
+        credits: u64,
+        cargo_capacity: u64,
+        jump_range: f64,
+        max_jumps: usize,
+    ) -> Vec<TradeRun> {
+        // Agents by the hash of their location, so that destinations are looked
+        // up without scanning all markets for each of them.
+        let agents = self
+            .markets
+            .iter()
+            .flat_map(|market| market.agents())
+            .map(|agent| (agent.lock().unwrap().hash(), agent))
+            .collect::<HashMap<_, _>>();
+
+        // What can be bought at the origin, and at what price.
+        let offers = match agents.get(&origin.hash()) {
+            Some(agent) => {
+                let agent = agent.lock().unwrap();
+                Commodity::values()
+                    .map(|commodity| {
+                        let price = agent.price(commodity, &self.config);
+                        let amount = agent
+                            .stock(commodity)
+                            .min(cargo_capacity)
+                            .min(credits / price.max(1));
+                        (commodity, price, amount)
+                    })
+                    .filter(|&(_, _, amount)| amount > 0)
+                    .collect::<Vec<_>>()
+            }
+            None => return vec![],
+        };
+        if offers.is_empty() {
+            return vec![];
+        }
+
+        let mut runs = vec![];
+        for (destination, jumps) in galaxy.reachable(origin, jump_range, max_jumps) {
+            if jumps == 0 {
+                continue;
+            }
+            let agent = match agents.get(&destination.hash()) {
+                Some(agent) => agent.lock().unwrap(),
+                None => continue,
+            };
+
+            for &(commodity, buy_price, amount) in &offers {
+                let sell_price = agent.price(commodity, &self.config);
+                if sell_price > buy_price {
+                    let profit = (sell_price - buy_price) * amount;
+                    runs.push(TradeRun {
+                        commodity: commodity.clone(),
+                        destination,
+                        jumps,
+                        amount,
+                        buy_price,
+                        sell_price,
+                        profit,
+                        profit_per_jump: profit as f64 / jumps as f64,
+                    });
+                }
+            }
+        }
+
+        runs.sort_by(|a, b| {
+            b.profit_per_jump
+                .partial_cmp(&a.profit_per_jump)
+                .unwrap_or(Ordering::Equal)
+        });
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::EconomyConfig;
+    use entities::{Faction, Sector};
+
+    #[test]
+    fn test_best_trades() {
+        // Food sells for more two jumps away, machinery only one jump away.
+        let (origin, near, far) = (Point::new(0., 0.), Point::new(3., 0.), Point::new(6., 0.));
+        let galaxy = Galaxy::new(
+            vec![Sector {
+                faction: Faction::Independent,
+                system_locations: vec![origin, near, far],
+            }],
+            vec![
+                System::empty_at(origin),
+                System::empty_at(near),
+                System::empty_at(far),
+            ],
+        );
+        let economy = Economy::new(&galaxy, &EconomyConfig::default());
+        let (food, machinery) = (Commodity::Food, Commodity::Machinery);
+        let prices = [
+            (origin, &food, 100),
+            (near, &food, 300),
+            (far, &food, 700),
+            (origin, &machinery, 1_000),
+            (near, &machinery, 1_100),
+            (far, &machinery, 1_000),
+        ];
+        for (location, commodity, price) in prices.iter() {
+            let system = galaxy.system(location).unwrap();
+            let mut agent = economy.agent(system).unwrap().lock().unwrap();
+            agent.set_price_belief(commodity, price - 50..price + 50);
+        }
+        {
+            let system = galaxy.system(&origin).unwrap();
+            let mut agent = economy.agent(system).unwrap().lock().unwrap();
+            agent.update_inventory(&food, 100);
+            agent.update_inventory(&machinery, 10);
+        }
+
+        let runs = economy.best_trades(&galaxy, &origin, 1_000_000, 50, 3.5, 2);
+        let ranking = runs
+            .iter()
+            .map(|run| (run.commodity.clone(), run.destination, run.amount))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranking,
+            vec![
+                (food.clone(), far, 50),
+                (food.clone(), near, 50),
+                (machinery.clone(), near, 10),
+            ]
+        );
+        assert_eq!(runs[0].profit, 30_000);
+        assert_eq!(runs[0].profit_per_jump, 15_000.);
+
+        // Amounts are limited by what the player can afford.
+        let runs = economy.best_trades(&galaxy, &origin, 1_000, 50, 3.5, 2);
+        let amounts = runs
+            .iter()
+            .map(|run| (run.commodity.clone(), run.amount))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            amounts,
+            vec![(food.clone(), 10), (food, 10), (machinery, 1)]
+        );
+    }
+}
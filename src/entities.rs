@@ -1,3 +1,4 @@
+use config::FactionsConfig;
 use game::Updatable;
 use rand::Rng;
 use rayon::iter::IntoParallelRefMutIterator;
@@ -22,9 +23,15 @@ pub enum Faction {
 }
 
 impl Faction {
-    /// Generate a random faction according to the "distribution".
-    pub fn random_faction<R: Rng>(gen: &mut R) -> Faction {
-        let probs = Categorical::new(&[15., 45., 30., 10.]).unwrap();
+    /// Generate a random faction according to the faction weights.
+    pub fn random_faction<R: Rng>(gen: &mut R, config: &FactionsConfig) -> Faction {
+        let probs = Categorical::new(&[
+            config.cartel.weight,
+            config.empire.weight,
+            config.federation.weight,
+            config.independent.weight,
+        ])
+        .unwrap();
 
         match probs.sample::<R>(gen) as usize {
             0 => Faction::Cartel,
@@ -78,6 +85,52 @@ impl Galaxy {
         self.systems.get(location)
     }
 
+    /// Returns a reference to the system with the given name, ignoring case.
+    pub fn system_by_name(&self, name: &str) -> Option<&System> {
+        self.systems
+            .values()
+            .find(|system| system.name.to_lowercase() == name.to_lowercase())
+    }
+
+    /// Returns the k systems closest to the given point which match the filter,
+    /// ordered by distance.
+    pub fn nearest_systems(&self, point: &Point, k: usize, filter: &SystemFilter) -> Vec<&System> {
+        self.map
+            .nearest_neighbor_iterator(point)
+            .filter_map(|location| self.system(location))
+            .filter(|system| filter.matches(system))
+            .take(k)
+            .collect()
+    }
+
+    /// Returns all systems within the radius of the given point which match
+    /// the filter, ordered by distance. Negative or non-finite radii match no
+    /// systems.
+    pub fn systems_within(
+        &self,
+        point: &Point,
+        radius: f64,
+        filter: &SystemFilter,
+    ) -> Vec<&System> {
+        if !radius.is_finite() || radius < 0. {
+            return vec![];
+        }
+        let mut systems = self
+            .map
+            .lookup_in_circle(point, &(radius * radius))
+            .into_iter()
+            .filter_map(|location| self.system(location))
+            .filter(|system| filter.matches(system))
+            .collect::<Vec<_>>();
+        systems.sort_by(|a, b| {
+            a.location
+                .distance(point)
+                .partial_cmp(&b.location.distance(point))
+                .unwrap()
+        });
+        systems
+    }
+
     /// Returns a mutable reference to the system at the given location.
     #[allow(dead_code)]
     pub fn system_mut(&mut self, location: &Point) -> Option<&mut System> {
@@ -121,40 +174,168 @@ impl Updatable for Galaxy {
 /// Represents a visitable planet in game with some attributes.
 pub struct Planet {
     pub name: String,
+    /// Mass in Earth masses.
+    pub mass: f64,
+    /// Radius in Earth radii.
+    pub radius: f64,
+    /// Surface gravity in g.
+    pub gravity: f64,
+    pub atmosphere: Atmosphere,
+    pub orbit: Orbit,
+    pub orbit_distance: f64,
+    /// Time to complete one orbit, in days.
+    pub orbital_period: f64,
+    pub surface_temperature: f64,
+    /// Suitability for human life, from 0 to 1 for Earth-like conditions.
+    pub habitability: f64,
+    pub planet_type: PlanetType,
+    pub economic_type: PlanetEconomy,
+    #[builder(default)]
+    pub moons: Vec<Moon>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Represents a moon orbiting a planet.
+pub struct Moon {
+    pub name: String,
+    /// Mass in Earth masses.
     pub mass: f64,
+    /// Radius in Earth radii.
+    pub radius: f64,
+    /// Surface gravity in g.
     pub gravity: f64,
+    pub atmosphere: Atmosphere,
+    /// Distance to the planet, in light seconds.
     pub orbit_distance: f64,
+    /// Time to complete one orbit around the planet, in days.
+    pub orbital_period: f64,
     pub surface_temperature: f64,
+    /// Suitability for human life, from 0 to 1 for Earth-like conditions.
+    pub habitability: f64,
     pub planet_type: PlanetType,
     pub economic_type: PlanetEconomy,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Represents the atmosphere of a planet or moon.
+pub struct Atmosphere {
+    pub kind: AtmosphereType,
+    /// Surface pressure in atmospheres, gas giants lack a surface and use the
+    /// pressure deep within their atmosphere.
+    pub pressure: f64,
+}
+
+impl Atmosphere {
+    /// Returns the lack of an atmosphere.
+    pub fn none() -> Atmosphere {
+        Atmosphere {
+            kind: AtmosphereType::None,
+            pressure: 0.,
+        }
+    }
+}
+
+impl fmt::Display for Atmosphere {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            AtmosphereType::None => write!(f, "No atmosphere"),
+            _ => write!(f, "{} atmosphere of {:.2} atm", self.kind, self.pressure),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-/// Different types of planet, i.e environments. Depends on surface_temperature
-/// and mass.
-pub enum PlanetType {
-    Metal,
-    Icy,
-    Rocky,
-    GasGiant,
-    Earth,
+/// Main gas of an atmosphere.
+pub enum AtmosphereType {
+    None,
+    /// Nitrogen and oxygen which humans can breathe.
+    Breathable,
+    CarbonDioxide,
+    Nitrogen,
+    Methane,
+    Hydrogen,
+}
+
+impl fmt::Display for AtmosphereType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                AtmosphereType::None => "None",
+                AtmosphereType::Breathable => "Breathable",
+                AtmosphereType::CarbonDioxide => "Carbon Dioxide",
+                AtmosphereType::Nitrogen => "Nitrogen",
+                AtmosphereType::Methane => "Methane",
+                AtmosphereType::Hydrogen => "Hydrogen",
+            }
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Represents a belt of asteroids orbiting the stars of a system.
+pub struct AsteroidBelt {
+    pub name: String,
+    /// Total mass of the asteroids, in Earth masses.
+    pub mass: f64,
+    pub orbit: Orbit,
+    pub orbit_distance: f64,
+    pub economic_type: PlanetEconomy,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Represents a space station in orbit around another body in its system.
+pub struct Station {
+    pub name: String,
+    pub orbits: Body,
+    pub economic_type: PlanetEconomy,
+    /// Whether the system market is located at the station.
+    pub market: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+/// Refers to a body of a system by its index.
+pub enum Body {
+    Star(usize),
+    Planet(usize),
+    /// Moon with the given index of the planet with the given index.
+    Moon(usize, usize),
+    Belt(usize),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+/// Describes what a planet orbits in a system with one or more stars.
+pub enum Orbit {
+    /// Orbits the star with the given index alone, an S-type orbit.
+    Star(usize),
+    /// Orbits the stars within its orbit, a circumbinary or P-type orbit.
+    Circumbinary,
+}
+
+impl fmt::Display for Orbit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Orbit::Star(index) => write!(f, "{}", Star::designation(index)),
+            Orbit::Circumbinary => write!(f, "Circumbinary"),
+        }
+    }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+/// Type of planet, i.e environment, named after one of the types in the
+/// planet types resource. Depends on surface_temperature and mass.
+pub struct PlanetType(pub String);
+
 impl fmt::Display for PlanetType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let security_str = match *self {
-            PlanetType::Metal => "Metal",
-            PlanetType::Icy => "Icy",
-            PlanetType::Rocky => "Rocky",
-            PlanetType::GasGiant => "Gas Giant",
-            PlanetType::Earth => "Earth",
-        };
-        write!(f, "{}", security_str)
+        write!(f, "{}", self.0)
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-/// Different types of planet economies, depends on the planet type.
+/// Different types of planet economies, the chance of each depends on the
+/// planet type.
 pub enum PlanetEconomy {
     Agriculture,
     Extraction,
@@ -194,42 +375,154 @@ pub struct Sector {
 #[derive(Serialize, Deserialize, Debug, Clone, Builder)]
 /// Represents a Star in a system.
 pub struct Star {
+    /// Mass in solar masses.
     pub mass: f64,
+    /// Luminosity in solar luminosities.
     pub luminosity: f64,
-    pub startype: StarType,
+    /// Radius in solar radii.
+    pub radius: f64,
+    /// Surface temperature in Kelvin.
+    pub temperature: f64,
+    /// Age in billions of years.
+    pub age: f64,
+    pub class: StarClass,
+    /// Orbital separation from the primary star of the system, in light
+    /// seconds. Zero for the primary star itself.
+    pub separation: f64,
 }
 
 impl Star {
-    pub fn new(mass: f64, luminosity: f64, kind: StarType) -> Self {
+    /// Surface temperature of the Sun, in Kelvin.
+    pub const SOLAR_TEMPERATURE: f64 = 5778.;
+    /// Radius of the Sun, in light seconds.
+    pub const SOLAR_RADIUS: f64 = 2.32;
+
+    /// Create a new primary star, with the surface temperature following from
+    /// the luminosity and radius.
+    pub fn new(mass: f64, luminosity: f64, radius: f64, age: f64, class: StarClass) -> Self {
         Star {
             mass,
             luminosity,
-            startype: kind,
+            radius,
+            temperature: Star::surface_temperature(luminosity, radius),
+            age,
+            class,
+            separation: 0.,
         }
     }
+
+    /// Returns the letter designating the star with the given index within its
+    /// system, A for the primary star.
+    pub fn designation(index: usize) -> char {
+        (b'A' + index as u8) as char
+    }
+
+    /// Returns the surface temperature of a black body with the given
+    /// luminosity and radius, by the Stefan-Boltzmann law.
+    pub fn surface_temperature(luminosity: f64, radius: f64) -> f64 {
+        if radius > 0. {
+            Star::SOLAR_TEMPERATURE * (luminosity / radius.powi(2)).powf(0.25)
+        } else {
+            0.
+        }
+    }
+
+    /// Returns the luminosity of a black body with the given radius and
+    /// surface temperature, by the Stefan-Boltzmann law.
+    pub fn black_body_luminosity(radius: f64, temperature: f64) -> f64 {
+        radius.powi(2) * (temperature / Star::SOLAR_TEMPERATURE).powi(4)
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-/// Describes the type of star: Single or Binary.
-pub enum StarType {
-    Single,
-    Binary,
+/// Classification of a star, either by spectral and luminosity class or as one
+/// of the stellar remnants.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum StarClass {
+    /// Star still fusing, with its spectral class, the subclass from 0 for
+    /// the hottest to 9 for the coolest, and its luminosity class.
+    Normal {
+        spectral: SpectralClass,
+        subclass: u8,
+        luminosity: LuminosityClass,
+    },
+    WhiteDwarf,
+    NeutronStar,
+    BlackHole,
+}
+
+impl StarClass {
+    /// Classifies a fusing star with the given surface temperature.
+    pub fn classify(temperature: f64, luminosity: LuminosityClass) -> Self {
+        let (spectral, hottest, coolest) = match temperature {
+            t if t >= 30_000. => (SpectralClass::O, 50_000., 30_000.),
+            t if t >= 10_000. => (SpectralClass::B, 30_000., 10_000.),
+            t if t >= 7_500. => (SpectralClass::A, 10_000., 7_500.),
+            t if t >= 6_000. => (SpectralClass::F, 7_500., 6_000.),
+            t if t >= 5_200. => (SpectralClass::G, 6_000., 5_200.),
+            t if t >= 3_700. => (SpectralClass::K, 5_200., 3_700.),
+            _ => (SpectralClass::M, 3_700., 2_400.),
+        };
+        let subclass = (10. * (hottest - temperature) / (hottest - coolest)).clamp(0., 9.) as u8;
+        StarClass::Normal {
+            spectral,
+            subclass,
+            luminosity,
+        }
+    }
+}
+
+impl fmt::Display for StarClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StarClass::Normal {
+                ref spectral,
+                subclass,
+                ref luminosity,
+            } => write!(f, "{:?}{}{}", spectral, subclass, luminosity),
+            StarClass::WhiteDwarf => write!(f, "White dwarf"),
+            StarClass::NeutronStar => write!(f, "Neutron star"),
+            StarClass::BlackHole => write!(f, "Black hole"),
+        }
+    }
+}
+
+/// Harvard spectral classes, from the hottest to the coolest.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SpectralClass {
+    O,
+    B,
+    A,
+    F,
+    G,
+    K,
+    M,
+}
+
+/// Yerkes luminosity classes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LuminosityClass {
+    Supergiant,
+    Giant,
+    Subgiant,
+    MainSequence,
 }
 
-impl fmt::Display for StarType {
+impl fmt::Display for LuminosityClass {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let type_str = match *self {
-            StarType::Single => "Single",
-            StarType::Binary => "Binary",
+        let class_str = match *self {
+            LuminosityClass::Supergiant => "I",
+            LuminosityClass::Giant => "III",
+            LuminosityClass::Subgiant => "IV",
+            LuminosityClass::MainSequence => "V",
         };
-        write!(f, "{}", type_str)
+        write!(f, "{}", class_str)
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Builder, Clone)]
 #[builder(field(public))]
 /// Represets a single star system with at a given location with the given
-/// star and planets.
+/// stars and planets.
 pub struct System {
     pub location: Point,
     pub name: String,
@@ -237,8 +530,69 @@ pub struct System {
     pub security: SystemSecurity,
     pub state: SystemState,
     pub reputation: Reputation,
-    pub star: Star,
+    /// Stars of the system, the primary star first.
+    pub stars: Vec<Star>,
     pub satelites: Vec<Planet>,
+    #[builder(default)]
+    pub belts: Vec<AsteroidBelt>,
+    #[builder(default)]
+    pub stations: Vec<Station>,
+}
+
+impl System {
+    /// Returns the name of the given body.
+    pub fn body_name(&self, body: Body) -> String {
+        match body {
+            Body::Star(index) => format!("{} {}", self.name, Star::designation(index)),
+            Body::Planet(index) => self.satelites[index].name.clone(),
+            Body::Moon(planet, index) => self.satelites[planet].moons[index].name.clone(),
+            Body::Belt(index) => self.belts[index].name.clone(),
+        }
+    }
+
+    /// Returns the station at which the market of the system is located, if
+    /// any.
+    pub fn market_station(&self) -> Option<&Station> {
+        self.stations.iter().find(|station| station.market)
+    }
+
+    /// Returns the economic type of every planet, moon, belt and station.
+    pub fn economies(&self) -> impl Iterator<Item = &PlanetEconomy> {
+        self.satelites
+            .iter()
+            .flat_map(|planet| {
+                Some(&planet.economic_type)
+                    .into_iter()
+                    .chain(planet.moons.iter().map(|moon| &moon.economic_type))
+            })
+            .chain(self.belts.iter().map(|belt| &belt.economic_type))
+            .chain(self.stations.iter().map(|station| &station.economic_type))
+    }
+}
+
+#[cfg(test)]
+impl System {
+    /// Creates an independent system at the location with a single sun-like
+    /// star and no bodies.
+    pub fn empty_at(location: Point) -> System {
+        SystemBuilder::default()
+            .location(location)
+            .name(format!("{} {}", location.x, location.y))
+            .faction(Faction::Independent)
+            .security(SystemSecurity::Low)
+            .state(SystemState::Boom)
+            .reputation(Reputation::default())
+            .stars(vec![Star::new(
+                1.,
+                1.,
+                1.,
+                4.6,
+                StarClass::classify(Star::SOLAR_TEMPERATURE, LuminosityClass::MainSequence),
+            )])
+            .satelites(vec![])
+            .build()
+            .unwrap()
+    }
 }
 
 impl Updatable for System {
@@ -263,8 +617,38 @@ impl PartialEq for System {
 
 impl Eq for System {}
 
+/// Filter used to select systems by attributes, unset attributes match any
+/// system.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SystemFilter {
+    pub faction: Option<Faction>,
+    pub security: Option<SystemSecurity>,
+    pub economy: Option<PlanetEconomy>,
+    /// Number of stars in the system.
+    pub stars: Option<usize>,
+}
+
+impl SystemFilter {
+    /// Returns true if the given system matches the filter, a system matches
+    /// an economy if any of its bodies has that economy.
+    pub fn matches(&self, system: &System) -> bool {
+        self.faction
+            .as_ref()
+            .is_none_or(|faction| *faction == system.faction)
+            && self
+                .security
+                .as_ref()
+                .is_none_or(|security| *security == system.security)
+            && self
+                .economy
+                .as_ref()
+                .is_none_or(|economy| system.economies().any(|other| other == economy))
+            && self.stars.is_none_or(|stars| stars == system.stars.len())
+    }
+}
+
 /// Represents the current player level of reputation with the system.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Reputation(i32);
 
 impl Updatable for Reputation {
@@ -279,12 +663,6 @@ impl Updatable for Reputation {
     }
 }
 
-impl Default for Reputation {
-    fn default() -> Reputation {
-        Reputation { 0: 0 }
-    }
-}
-
 impl fmt::Display for Reputation {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let out_str = match self.0 {
@@ -300,7 +678,7 @@ impl fmt::Display for Reputation {
 }
 
 /// Represents the different security levels a system is in at a given point.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum SystemSecurity {
     Anarchy,
     Low,
@@ -346,3 +724,51 @@ impl fmt::Display for SystemState {
         write!(f, "{}", state_str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::GameConfig;
+    use generators::generate_galaxy;
+
+    #[test]
+    fn test_spatial_queries() {
+        let config = GameConfig {
+            number_of_systems: 500,
+            number_of_sectors: 5,
+            ..GameConfig::default()
+        };
+        let galaxy = generate_galaxy(&config).unwrap();
+        let point = Point::new(10., -20.);
+        let filter = SystemFilter {
+            security: Some(SystemSecurity::Low),
+            ..SystemFilter::default()
+        };
+
+        // Compare against a linear scan over all systems.
+        let mut expected = galaxy
+            .systems()
+            .filter(|system| filter.matches(system))
+            .collect::<Vec<_>>();
+        expected.sort_by(|a, b| {
+            a.location
+                .distance(&point)
+                .partial_cmp(&b.location.distance(&point))
+                .unwrap()
+        });
+
+        let nearest = galaxy.nearest_systems(&point, 10, &filter);
+        assert_eq!(nearest, expected[..10].to_vec());
+
+        // Pick a radius between two systems, as the system exactly on the
+        // radius may be excluded due to rounding.
+        let radius =
+            (expected[20].location.distance(&point) + expected[21].location.distance(&point)) / 2.;
+        let within = galaxy.systems_within(&point, radius, &filter);
+        assert_eq!(within, expected[..21].to_vec());
+        assert!(galaxy.systems_within(&point, -radius, &filter).is_empty());
+        assert!(galaxy
+            .systems_within(&point, f64::INFINITY, &filter)
+            .is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+use std::{error::Error, fmt, str::FromStr};
+
+mod svg;
+mod tables;
+
+pub use self::svg::render_svg;
+pub use self::tables::export_tables;
+
+/// Image formats the galaxy map can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapFormat {
+    Svg,
+}
+
+/// File formats the galaxy data tables can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableFormat {
+    JsonLines,
+    Csv,
+}
+
+impl TableFormat {
+    /// Returns the file extension used for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            TableFormat::JsonLines => "jsonl",
+            TableFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Error returned when parsing an unsupported export format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownFormat(String);
+
+impl fmt::Display for UnknownFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown export format: {}", self.0)
+    }
+}
+
+impl Error for UnknownFormat {}
+
+impl FromStr for MapFormat {
+    type Err = UnknownFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "svg" => Ok(MapFormat::Svg),
+            _ => Err(UnknownFormat(String::from(s))),
+        }
+    }
+}
+
+impl FromStr for TableFormat {
+    type Err = UnknownFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jsonl" | "json" => Ok(TableFormat::JsonLines),
+            "csv" => Ok(TableFormat::Csv),
+            _ => Err(UnknownFormat(String::from(s))),
+        }
+    }
+}
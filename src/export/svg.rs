@@ -0,0 +1,149 @@
+use std::io::{self, Write};
+
+use entities::{Faction, Galaxy, System};
+use utils::{convex_hull, Point};
+
+/// Width of the exported image in pixels, the height follows the aspect ratio
+/// of the galaxy.
+const IMAGE_WIDTH: f64 = 1600.;
+
+/// Returns the color used to draw the given faction.
+fn faction_color(faction: &Faction) -> &'static str {
+    match *faction {
+        Faction::Empire => "#d62728",
+        Faction::Federation => "#1f77b4",
+        Faction::Cartel => "#e5c100",
+        Faction::Independent => "#2ca02c",
+    }
+}
+
+/// Escapes text for use in XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the galaxy as an SVG image, with every system drawn as a dot colored
+/// by faction and every sector outlined by its convex hull. System names are
+/// drawn next to the dots if labels is set.
+pub fn render_svg<W: Write>(galaxy: &Galaxy, labels: bool, out: &mut W) -> io::Result<()> {
+    let mut systems = galaxy.systems().collect::<Vec<_>>();
+    systems.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // Bounding box of all systems, with some margin.
+    let (mut min, mut max) = match systems.first() {
+        Some(system) => (system.location, system.location),
+        None => (Point::origin(), Point::origin()),
+    };
+    for system in &systems {
+        min.x = min.x.min(system.location.x);
+        min.y = min.y.min(system.location.y);
+        max.x = max.x.max(system.location.x);
+        max.y = max.y.max(system.location.y);
+    }
+    let margin = ((max.x - min.x).max(max.y - min.y) * 0.05).max(1.);
+    let (width, height) = (max.x - min.x + 2. * margin, max.y - min.y + 2. * margin);
+    let radius = width / 800.;
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="{} {} {} {}">"#,
+        IMAGE_WIDTH,
+        IMAGE_WIDTH * height / width,
+        min.x - margin,
+        min.y - margin,
+        width,
+        height
+    )?;
+    writeln!(
+        out,
+        r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#0b0c1a"/>"##,
+        min.x - margin,
+        min.y - margin,
+        width,
+        height
+    )?;
+
+    writeln!(out, r#"<g id="sectors" stroke-width="{}">"#, radius / 2.)?;
+    for sector in &galaxy.sectors {
+        let hull = convex_hull(&sector.system_locations);
+        let points = hull
+            .iter()
+            .map(|point| format!("{},{}", point.x, point.y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let color = faction_color(&sector.faction);
+        writeln!(
+            out,
+            r#"<polygon points="{}" fill="{}" fill-opacity="0.15" stroke="{}"/>"#,
+            points, color, color
+        )?;
+    }
+    writeln!(out, "</g>")?;
+
+    writeln!(out, r#"<g id="systems">"#)?;
+    for system in &systems {
+        writeln!(
+            out,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}"><title>{}</title></circle>"#,
+            system.location.x,
+            system.location.y,
+            radius,
+            faction_color(&system.faction),
+            escape(&system.name)
+        )?;
+    }
+    writeln!(out, "</g>")?;
+
+    if labels {
+        write_labels(out, &systems, radius)?;
+    }
+    writeln!(out, "</svg>")
+}
+
+/// Writes the name of each system next to its dot.
+fn write_labels<W: Write>(out: &mut W, systems: &[&System], radius: f64) -> io::Result<()> {
+    writeln!(
+        out,
+        r##"<g id="labels" fill="#d0d0d0" font-family="sans-serif" font-size="{}">"##,
+        radius * 3.
+    )?;
+    for system in systems {
+        writeln!(
+            out,
+            r#"<text x="{}" y="{}">{}</text>"#,
+            system.location.x + radius * 1.5,
+            system.location.y + radius,
+            escape(&system.name)
+        )?;
+    }
+    writeln!(out, "</g>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::GameConfig;
+    use generators::generate_galaxy;
+
+    #[test]
+    fn test_render_svg() {
+        let config = GameConfig {
+            number_of_systems: 20,
+            number_of_sectors: 2,
+            ..GameConfig::default()
+        };
+        let galaxy = generate_galaxy(&config).unwrap();
+        let mut out = vec![];
+        render_svg(&galaxy, true, &mut out).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<polygon ").count(), 2);
+        assert_eq!(svg.matches("<circle ").count(), 20);
+        assert_eq!(svg.matches("<text ").count(), 20);
+    }
+}
@@ -0,0 +1,502 @@
+use serde::Serialize;
+use serde_json;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use super::TableFormat;
+use economy::Economy;
+use entities::{Galaxy, Star};
+use utils::Point;
+
+/// A single row of an exported table.
+trait Row: Serialize {
+    /// Names of the columns, in the same order as the fields.
+    const COLUMNS: &'static [&'static str];
+
+    /// Returns the values of each column formatted for CSV.
+    fn fields(&self) -> Vec<String>;
+}
+
+#[derive(Serialize, Default)]
+struct SystemRow {
+    name: String,
+    x: f64,
+    y: f64,
+    sector: usize,
+    faction: String,
+    security: String,
+    state: String,
+    reputation: String,
+    planets: usize,
+}
+
+impl Row for SystemRow {
+    const COLUMNS: &'static [&'static str] = &[
+        "name",
+        "x",
+        "y",
+        "sector",
+        "faction",
+        "security",
+        "state",
+        "reputation",
+        "planets",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.x.to_string(),
+            self.y.to_string(),
+            self.sector.to_string(),
+            self.faction.clone(),
+            self.security.clone(),
+            self.state.clone(),
+            self.reputation.clone(),
+            self.planets.to_string(),
+        ]
+    }
+}
+
+#[derive(Serialize, Default)]
+struct StarRow {
+    system: String,
+    designation: String,
+    class: String,
+    mass: f64,
+    luminosity: f64,
+    radius: f64,
+    temperature: f64,
+    age: f64,
+    separation: f64,
+}
+
+impl Row for StarRow {
+    const COLUMNS: &'static [&'static str] = &[
+        "system",
+        "designation",
+        "class",
+        "mass",
+        "luminosity",
+        "radius",
+        "temperature",
+        "age",
+        "separation",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.system.clone(),
+            self.designation.clone(),
+            self.class.clone(),
+            self.mass.to_string(),
+            self.luminosity.to_string(),
+            self.radius.to_string(),
+            self.temperature.to_string(),
+            self.age.to_string(),
+            self.separation.to_string(),
+        ]
+    }
+}
+
+#[derive(Serialize, Default)]
+struct PlanetRow {
+    system: String,
+    name: String,
+    mass: f64,
+    radius: f64,
+    gravity: f64,
+    atmosphere: String,
+    pressure: f64,
+    orbit: String,
+    orbit_distance: f64,
+    orbital_period: f64,
+    surface_temperature: f64,
+    habitability: f64,
+    planet_type: String,
+    economy: String,
+}
+
+impl Row for PlanetRow {
+    const COLUMNS: &'static [&'static str] = &[
+        "system",
+        "name",
+        "mass",
+        "radius",
+        "gravity",
+        "atmosphere",
+        "pressure",
+        "orbit",
+        "orbit_distance",
+        "orbital_period",
+        "surface_temperature",
+        "habitability",
+        "planet_type",
+        "economy",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.system.clone(),
+            self.name.clone(),
+            self.mass.to_string(),
+            self.radius.to_string(),
+            self.gravity.to_string(),
+            self.atmosphere.clone(),
+            self.pressure.to_string(),
+            self.orbit.clone(),
+            self.orbit_distance.to_string(),
+            self.orbital_period.to_string(),
+            self.surface_temperature.to_string(),
+            self.habitability.to_string(),
+            self.planet_type.clone(),
+            self.economy.clone(),
+        ]
+    }
+}
+
+#[derive(Serialize, Default)]
+struct BodyRow {
+    system: String,
+    name: String,
+    kind: String,
+    orbits: String,
+    economy: String,
+    market: bool,
+}
+
+impl Row for BodyRow {
+    const COLUMNS: &'static [&'static str] =
+        &["system", "name", "kind", "orbits", "economy", "market"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.system.clone(),
+            self.name.clone(),
+            self.kind.clone(),
+            self.orbits.clone(),
+            self.economy.clone(),
+            self.market.to_string(),
+        ]
+    }
+}
+
+#[derive(Serialize, Default)]
+struct SectorRow {
+    sector: usize,
+    faction: String,
+    systems: usize,
+    center_x: f64,
+    center_y: f64,
+}
+
+impl Row for SectorRow {
+    const COLUMNS: &'static [&'static str] =
+        &["sector", "faction", "systems", "center_x", "center_y"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.sector.to_string(),
+            self.faction.clone(),
+            self.systems.to_string(),
+            self.center_x.to_string(),
+            self.center_y.to_string(),
+        ]
+    }
+}
+
+#[derive(Serialize, Default)]
+struct PriceRow {
+    system: String,
+    commodity: String,
+    price: u64,
+    stock: u64,
+}
+
+impl Row for PriceRow {
+    const COLUMNS: &'static [&'static str] = &["system", "commodity", "price", "stock"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.system.clone(),
+            self.commodity.clone(),
+            self.price.to_string(),
+            self.stock.to_string(),
+        ]
+    }
+}
+
+/// Quotes a CSV field if needed.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+/// Writes the rows to a file named after the table in the given directory,
+/// returning the path of the file.
+fn write_table<R: Row>(
+    dir: &Path,
+    table: &str,
+    format: TableFormat,
+    rows: &[R],
+) -> io::Result<PathBuf> {
+    let path = dir.join(format!("{}.{}", table, format.extension()));
+    let mut out = BufWriter::new(File::create(&path)?);
+
+    match format {
+        TableFormat::JsonLines => {
+            for row in rows {
+                writeln!(out, "{}", serde_json::to_string(row)?)?;
+            }
+        }
+        TableFormat::Csv => {
+            writeln!(out, "{}", R::COLUMNS.join(","))?;
+            for row in rows {
+                let fields = row
+                    .fields()
+                    .iter()
+                    .map(|field| csv_field(field))
+                    .collect::<Vec<_>>();
+                writeln!(out, "{}", fields.join(","))?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(path)
+}
+
+/// Exports the systems, stars, planets, other bodies, sectors and market prices of the game
+/// as one table each in the given directory, returning the paths written.
+pub fn export_tables(
+    galaxy: &Galaxy,
+    economy: &Economy,
+    format: TableFormat,
+    dir: &Path,
+) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+
+    let sector_of = galaxy
+        .sectors
+        .iter()
+        .enumerate()
+        .flat_map(|(index, sector)| {
+            sector
+                .system_locations
+                .iter()
+                .map(move |location| (*location, index))
+        })
+        .collect::<HashMap<Point, usize>>();
+    let mut systems = galaxy.systems().collect::<Vec<_>>();
+    systems.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let system_rows = systems
+        .iter()
+        .map(|system| {
+            let sector = *sector_of.get(&system.location).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("System {} is not part of any sector", system.name),
+                )
+            })?;
+            Ok(SystemRow {
+                name: system.name.clone(),
+                x: system.location.x,
+                y: system.location.y,
+                sector,
+                faction: system.faction.to_string(),
+                security: system.security.to_string(),
+                state: system.state.to_string(),
+                reputation: system.reputation.to_string(),
+                planets: system.satelites.len(),
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let star_rows = systems
+        .iter()
+        .flat_map(|system| {
+            system
+                .stars
+                .iter()
+                .enumerate()
+                .map(move |(index, star)| StarRow {
+                    system: system.name.clone(),
+                    designation: Star::designation(index).to_string(),
+                    class: star.class.to_string(),
+                    mass: star.mass,
+                    luminosity: star.luminosity,
+                    radius: star.radius,
+                    temperature: star.temperature,
+                    age: star.age,
+                    separation: star.separation,
+                })
+        })
+        .collect::<Vec<_>>();
+    let planet_rows = systems
+        .iter()
+        .flat_map(|system| {
+            system.satelites.iter().map(move |planet| PlanetRow {
+                system: system.name.clone(),
+                name: planet.name.clone(),
+                mass: planet.mass,
+                radius: planet.radius,
+                gravity: planet.gravity,
+                atmosphere: planet.atmosphere.kind.to_string(),
+                pressure: planet.atmosphere.pressure,
+                orbit: planet.orbit.to_string(),
+                orbit_distance: planet.orbit_distance,
+                orbital_period: planet.orbital_period,
+                surface_temperature: planet.surface_temperature,
+                habitability: planet.habitability,
+                planet_type: planet.planet_type.to_string(),
+                economy: planet.economic_type.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let body_rows = systems
+        .iter()
+        .flat_map(|system| {
+            let moons = system.satelites.iter().flat_map(move |planet| {
+                planet.moons.iter().map(move |moon| BodyRow {
+                    system: system.name.clone(),
+                    name: moon.name.clone(),
+                    kind: String::from("moon"),
+                    orbits: planet.name.clone(),
+                    economy: moon.economic_type.to_string(),
+                    market: false,
+                })
+            });
+            let belts = system.belts.iter().map(move |belt| BodyRow {
+                system: system.name.clone(),
+                name: belt.name.clone(),
+                kind: String::from("belt"),
+                orbits: belt.orbit.to_string(),
+                economy: belt.economic_type.to_string(),
+                market: false,
+            });
+            let stations = system.stations.iter().map(move |station| BodyRow {
+                system: system.name.clone(),
+                name: station.name.clone(),
+                kind: String::from("station"),
+                orbits: system.body_name(station.orbits),
+                economy: station.economic_type.to_string(),
+                market: station.market,
+            });
+            moons.chain(belts).chain(stations)
+        })
+        .collect::<Vec<_>>();
+    let sector_rows = galaxy
+        .sectors
+        .iter()
+        .enumerate()
+        .map(|(index, sector)| {
+            let count = sector.system_locations.len();
+            let sum = sector
+                .system_locations
+                .iter()
+                .fold(Point::origin(), |sum, location| sum + *location);
+            SectorRow {
+                sector: index,
+                faction: sector.faction.to_string(),
+                systems: count,
+                center_x: sum.x / count.max(1) as f64,
+                center_y: sum.y / count.max(1) as f64,
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut listings = economy.all_listings();
+    let price_rows = systems
+        .iter()
+        .flat_map(|system| {
+            listings
+                .remove(&system.location.hash())
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |(commodity, price, stock)| PriceRow {
+                    system: system.name.clone(),
+                    commodity: commodity.to_string(),
+                    price,
+                    stock,
+                })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![
+        write_table(dir, "systems", format, &system_rows)?,
+        write_table(dir, "stars", format, &star_rows)?,
+        write_table(dir, "planets", format, &planet_rows)?,
+        write_table(dir, "bodies", format, &body_rows)?,
+        write_table(dir, "sectors", format, &sector_rows)?,
+        write_table(dir, "prices", format, &price_rows)?,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::GameConfig;
+    use economy::Commodity;
+    use generators::generate_galaxy;
+    use std::env;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("Levich I"), "Levich I");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    /// Checks that the columns of the row match its CSV fields and the keys
+    /// of its JSON object.
+    fn check_columns<R: Row + Default>() {
+        let row = R::default();
+        assert_eq!(R::COLUMNS.len(), row.fields().len());
+        let value = serde_json::to_value(&row).unwrap();
+        let mut keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+        let mut columns = R::COLUMNS.to_vec();
+        keys.sort();
+        columns.sort_unstable();
+        assert_eq!(keys, columns);
+    }
+
+    #[test]
+    fn test_columns() {
+        check_columns::<SystemRow>();
+        check_columns::<StarRow>();
+        check_columns::<PlanetRow>();
+        check_columns::<BodyRow>();
+        check_columns::<SectorRow>();
+        check_columns::<PriceRow>();
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let config = GameConfig {
+            number_of_systems: 20,
+            number_of_sectors: 2,
+            ..GameConfig::default()
+        };
+        let galaxy = generate_galaxy(&config).unwrap();
+        let economy = Economy::new(&galaxy, &config.economy);
+        let dir = env::temp_dir().join(format!("gemini-export-{}", std::process::id()));
+        let paths = export_tables(&galaxy, &economy, TableFormat::Csv, &dir).unwrap();
+        assert_eq!(paths.len(), 6);
+
+        let systems = fs::read_to_string(dir.join("systems.csv")).unwrap();
+        let lines = systems.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], SystemRow::COLUMNS.join(","));
+        assert_eq!(lines.len(), 1 + 20);
+
+        let prices = fs::read_to_string(dir.join("prices.csv")).unwrap();
+        let lines = prices.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], PriceRow::COLUMNS.join(","));
+        assert_eq!(lines.len(), 1 + 20 * Commodity::values().count());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
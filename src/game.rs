@@ -1,17 +1,55 @@
+use anyhow::{bail, Context, Result};
+use bincode;
 use chrono::{DateTime, Duration, TimeZone, Utc};
 use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
     sync::{Arc, Mutex},
     time::Instant,
 };
 
-use economy::Economy;
+use clock::Clock;
+use economy::{Commodity, Economy, Trade, TradeError, TradeRun};
 use entities::Galaxy;
+use navigation::{NavigationError, Route, RouteObjective};
+use player::Player;
+use utils::Point;
+
+/// Magic bytes identifying a save file.
+const SAVE_MAGIC: &[u8; 4] = b"GMNI";
+
+/// Version of the save file format, must be bumped whenever the serialized
+/// layout of any persisted type changes.
+pub const SAVE_VERSION: u32 = 11;
 
 /// Main game state object, shared and syncronized by use of Arc and Mutex.
 pub struct Game {
     pub galaxy: Mutex<Galaxy>,
     pub economy: Mutex<Economy>,
+    pub player: Mutex<Player>,
     updated: Mutex<DateTime<Utc>>,
+    clock: Mutex<Clock>,
+}
+
+/// Borrowed view of the game state, used when writing a save file.
+#[derive(Serialize)]
+struct SaveState<'a> {
+    updated: &'a DateTime<Utc>,
+    clock: &'a Clock,
+    galaxy: &'a Galaxy,
+    economy: &'a Economy,
+    player: &'a Player,
+}
+
+/// Owned game state as read back from a save file.
+#[derive(Deserialize)]
+struct LoadState {
+    updated: DateTime<Utc>,
+    clock: Clock,
+    galaxy: Galaxy,
+    economy: Economy,
+    player: Player,
 }
 
 impl Game {
@@ -20,45 +58,203 @@ impl Game {
         Arc::new(Game {
             galaxy: Mutex::new(Galaxy::default()),
             economy: Mutex::new(Economy::default()),
+            player: Mutex::new(Player::default()),
             updated: Mutex::new(Utc.ymd(2021, 1, 1).and_hms(0, 0, 0)), // Start time
+            clock: Mutex::new(Clock::default()),
         })
     }
 
-    /// Update Game information, may advance time.
+    /// Writes the full game state to the save file at the given path.
+    /// The state is first written to a temporary file which then replaces any
+    /// previous save, so that an interrupted save never corrupts the old one.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let now = Instant::now();
+        let tmp_path = path.with_extension("tmp");
+        {
+            let updated = self.updated.lock().unwrap();
+            let clock = self.clock.lock().unwrap();
+            let galaxy = self.galaxy.lock().unwrap();
+            let economy = self.economy.lock().unwrap();
+            let player = self.player.lock().unwrap();
+
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            writer.write_all(SAVE_MAGIC)?;
+            bincode::serialize_into(&mut writer, &SAVE_VERSION)?;
+            bincode::serialize_into(
+                &mut writer,
+                &SaveState {
+                    updated: &updated,
+                    clock: &clock,
+                    galaxy: &galaxy,
+                    economy: &economy,
+                    player: &player,
+                },
+            )?;
+            writer.flush()?;
+        }
+        fs::rename(&tmp_path, path)?;
+
+        debug!(
+            "Saved game to {}, took {} ms",
+            path.display(),
+            ((now.elapsed().as_secs() * 1_000) + u64::from(now.elapsed().subsec_millis()))
+        );
+        Ok(())
+    }
+
+    /// Restores a game from the save file at the given path.
+    pub fn load(path: &Path) -> Result<Arc<Self>> {
+        let now = Instant::now();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open save file {}", path.display()))?;
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != SAVE_MAGIC {
+            bail!("{} is not a save file", path.display());
+        }
+
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != SAVE_VERSION {
+            bail!(
+                "Save file {} has version {}, expected version {}",
+                path.display(),
+                version,
+                SAVE_VERSION
+            );
+        }
+
+        let state: LoadState = bincode::deserialize_from(&mut reader)?;
+        debug!(
+            "Loaded game from {}, took {} ms",
+            path.display(),
+            ((now.elapsed().as_secs() * 1_000) + u64::from(now.elapsed().subsec_millis()))
+        );
+
+        Ok(Arc::new(Game {
+            galaxy: Mutex::new(state.galaxy),
+            economy: Mutex::new(state.economy),
+            player: Mutex::new(state.player),
+            updated: Mutex::new(state.updated),
+            clock: Mutex::new(state.clock),
+        }))
+    }
+
+    /// Returns the in game time of the last update.
+    pub fn updated(&self) -> DateTime<Utc> {
+        *self.updated.lock().unwrap()
+    }
+
+    /// Buys the given amount of a commodity at the player's current system.
+    pub fn buy(&self, commodity: &Commodity, amount: u64) -> Result<Trade, TradeError> {
+        let galaxy = self.galaxy.lock().unwrap();
+        let mut player = self.player.lock().unwrap();
+        let system = galaxy
+            .system(&player.location())
+            .ok_or(TradeError::NoMarket)?;
+        self.economy
+            .lock()
+            .unwrap()
+            .buy(&mut player, system, commodity, amount)
+    }
+
+    /// Sells the given amount of a commodity at the player's current system.
+    pub fn sell(&self, commodity: &Commodity, amount: u64) -> Result<Trade, TradeError> {
+        let galaxy = self.galaxy.lock().unwrap();
+        let mut player = self.player.lock().unwrap();
+        let system = galaxy
+            .system(&player.location())
+            .ok_or(TradeError::NoMarket)?;
+        self.economy
+            .lock()
+            .unwrap()
+            .sell(&mut player, system, commodity, amount)
+    }
+
+    /// Returns the most profitable commodity runs from the player's current
+    /// system using the player's ship and credits, at most max_jumps away.
+    pub fn best_trades(&self, max_jumps: usize) -> Vec<TradeRun> {
+        let galaxy = self.galaxy.lock().unwrap();
+        let player = self.player.lock().unwrap();
+        self.economy.lock().unwrap().best_trades(
+            &galaxy,
+            &player.location(),
+            player.credits(),
+            player.ship().cargo_capacity,
+            player.ship().jump_range,
+            max_jumps,
+        )
+    }
+
+    /// Plans a route from the player's current system to the destination
+    /// within the jump range of the player's ship.
+    pub fn route(
+        &self,
+        destination: &Point,
+        objective: RouteObjective,
+    ) -> Result<Route, NavigationError> {
+        let galaxy = self.galaxy.lock().unwrap();
+        let player = self.player.lock().unwrap();
+        galaxy.route(
+            &player.location(),
+            destination,
+            player.ship().jump_range,
+            objective,
+        )
+    }
+
+    /// Jumps the player directly to the system at the destination.
+    pub fn jump(&self, destination: &Point) -> Result<(), NavigationError> {
+        let galaxy = self.galaxy.lock().unwrap();
+        if galaxy.system(destination).is_none() {
+            return Err(NavigationError::UnknownSystem(*destination));
+        }
+        self.player.lock().unwrap().jump(destination)
+    }
+
+    /// Update Game information, advancing time as decided by the clock.
     pub fn update(&self) {
-        // If we have advanced time some steps.
-        self.attempt_advance_time();
+        let days = self.clock.lock().unwrap().tick(Utc::now());
+        self.advance(days);
+    }
+
+    /// Returns the clock deciding how time advances on updates.
+    pub fn clock(&self) -> Clock {
+        *self.clock.lock().unwrap()
+    }
+
+    /// Replaces the clock deciding how time advances on updates.
+    pub fn set_clock(&self, clock: Clock) {
+        *self.clock.lock().unwrap() = clock;
     }
 
-    /// Attemps to advance time returning the number of days advanced if any.
-    fn attempt_advance_time(&self) -> Option<i64> {
+    /// Advances time exactly the given number of days, updating the game state
+    /// once per day.
+    pub fn advance(&self, days: u32) {
+        if days == 0 {
+            return;
+        }
         let updated: &mut DateTime<Utc> = &mut self.updated.lock().unwrap();
-        // Check if we need to advance time.
-        let days_passed = Utc::now().signed_duration_since(*updated).num_days();
-        if days_passed > 0 {
-            // Measure time for generation.
-            let now = Instant::now();
-            debug!("Advancing time: {} steps", days_passed);
-
-            // Update state iterativly.
-            for _ in 0..days_passed {
-                self.galaxy.lock().unwrap().update();
-                self.economy.lock().unwrap().update();
-            }
-
-            // Update last update timer.
-            *updated = updated
-                .checked_add_signed(Duration::days(days_passed))
-                .unwrap();
-            //self.save_all();
-            debug!(
-                "Time advancement finished, took {} ms",
-                ((now.elapsed().as_secs() * 1_000) + u64::from(now.elapsed().subsec_millis()))
-            );
-            Some(days_passed)
-        } else {
-            None
+
+        // Measure time for generation.
+        let now = Instant::now();
+        debug!("Advancing time: {} steps", days);
+
+        // Update state iterativly.
+        for _ in 0..days {
+            self.galaxy.lock().unwrap().update();
+            self.economy.lock().unwrap().update();
         }
+
+        // Update last update timer.
+        *updated = updated
+            .checked_add_signed(Duration::days(i64::from(days)))
+            .unwrap();
+        debug!(
+            "Time advancement finished, took {} ms",
+            ((now.elapsed().as_secs() * 1_000) + u64::from(now.elapsed().subsec_millis()))
+        );
     }
 }
 
@@ -67,3 +263,81 @@ pub trait Updatable {
     /// Performs an update for one time step.
     fn update(&mut self);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::GameConfig;
+    use generators::generate_galaxy;
+    use std::env;
+
+    #[test]
+    fn test_save_load_roundtrip() {
+        let config = GameConfig {
+            number_of_systems: 300,
+            number_of_sectors: 5,
+            ..GameConfig::default()
+        };
+        let game = Game::new();
+        let galaxy = generate_galaxy(&config).unwrap();
+        *game.economy.lock().unwrap() = Economy::new(&galaxy, &config.economy);
+        *game.galaxy.lock().unwrap() = galaxy;
+        game.economy.lock().unwrap().update();
+
+        let path = env::temp_dir().join(format!("gemini-test-{}.sav", std::process::id()));
+        game.save(&path).unwrap();
+        let loaded = Game::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(game.updated(), loaded.updated());
+        assert_eq!(
+            game.player.lock().unwrap().location(),
+            loaded.player.lock().unwrap().location()
+        );
+
+        let galaxy = game.galaxy.lock().unwrap();
+        let loaded_galaxy = loaded.galaxy.lock().unwrap();
+        assert_eq!(galaxy.sectors, loaded_galaxy.sectors);
+        assert_eq!(galaxy.systems.len(), loaded_galaxy.systems.len());
+
+        let economy = game.economy.lock().unwrap();
+        let loaded_economy = loaded.economy.lock().unwrap();
+        assert_eq!(game.clock(), loaded.clock());
+        for system in galaxy.systems() {
+            let loaded_system = loaded_galaxy.system(&system.location).unwrap();
+            assert_eq!(system.name, loaded_system.name);
+            assert_eq!(
+                economy.commodity_prices(system),
+                loaded_economy.commodity_prices(loaded_system)
+            );
+            assert_eq!(
+                economy.populations(system),
+                loaded_economy.populations(loaded_system)
+            );
+        }
+    }
+
+    #[test]
+    fn test_advance() {
+        let config = GameConfig {
+            number_of_systems: 100,
+            number_of_sectors: 2,
+            ..GameConfig::default()
+        };
+        let game = Game::new();
+        let galaxy = generate_galaxy(&config).unwrap();
+        *game.economy.lock().unwrap() = Economy::new(&galaxy, &config.economy);
+        *game.galaxy.lock().unwrap() = galaxy;
+        game.set_clock(Clock::Manual);
+
+        let start = game.updated();
+        game.update();
+        assert_eq!(game.updated(), start);
+        game.advance(5);
+        assert_eq!(game.updated(), start + Duration::days(5));
+
+        game.set_clock(Clock::Fixed { days_per_update: 2 });
+        game.update();
+        assert_eq!(game.updated(), start + Duration::days(7));
+    }
+}
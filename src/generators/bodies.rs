@@ -0,0 +1,251 @@
+use rand::Rng;
+use statrs::distribution::{Distribution, Poisson};
+
+use config::PlanetConfig;
+use entities::{
+    AsteroidBelt, AtmosphereType, Body, Moon, Orbit, PlanetBuilder, PlanetEconomy, PlanetType,
+    Star, Station, System,
+};
+use generators::planets::PlanetGen;
+use resources::PlanetTypeInfo;
+
+/// Used for generating the moons, asteroid belts and stations of a system.
+pub struct BodyGen {
+    mean_moons: f64,
+    mean_giant_moons: f64,
+    belt_chance: f64,
+}
+
+impl BodyGen {
+    /// Chance of a gas giant without an economy having a refinery outpost
+    /// harvesting its atmosphere.
+    const GAS_OUTPOST_CHANCE: f64 = 0.5;
+    /// Largest number of moons of a planet, which are named by letter.
+    const MAX_MOONS: usize = 26;
+
+    /// Create a new body generator.
+    pub fn new(config: &PlanetConfig) -> Self {
+        BodyGen {
+            mean_moons: config.mean_moons,
+            mean_giant_moons: config.mean_giant_moons,
+            belt_chance: config.belt_chance,
+        }
+    }
+
+    /// Generates the moons of a planet, which share the surface temperature
+    /// of the planet. Giants have more moons than other planets.
+    pub fn generate_moons<R: Rng>(
+        &self,
+        gen: &mut R,
+        planet_gen: &PlanetGen,
+        kind: &PlanetTypeInfo,
+        mass: f64,
+        surface_temperature: f64,
+    ) -> Vec<Moon> {
+        let mean = if kind.giant {
+            self.mean_giant_moons
+        } else {
+            self.mean_moons
+        };
+        if mean <= 0. {
+            return vec![];
+        }
+        let count = (Poisson::new(mean).unwrap().sample(gen) as usize).min(BodyGen::MAX_MOONS);
+
+        let mut moons = (0..count)
+            .map(|_| {
+                // Moons weigh between a thousandth and a thirtieth of the
+                // planet, and orbit it within a few light seconds.
+                let moon_mass = mass * 10f64.powf(-1.5 - 1.5 * gen.gen::<f64>());
+                let orbit_distance = 0.2 * 10f64.powf(1.5 * gen.gen::<f64>());
+                let moon_type = planet_gen.predict_type(gen, surface_temperature, moon_mass);
+                let radius = PlanetGen::calculate_radius(moon_mass, moon_type);
+                let gravity = PlanetGen::calculate_gravity(moon_mass, radius);
+                let atmosphere = PlanetGen::predict_atmosphere(
+                    gen,
+                    moon_type,
+                    moon_mass,
+                    radius,
+                    surface_temperature,
+                );
+                let habitability =
+                    PlanetGen::calculate_habitability(surface_temperature, gravity, &atmosphere);
+                let economic_type = PlanetGen::predict_economy(gen, moon_type);
+                Moon {
+                    name: String::new(),
+                    mass: moon_mass,
+                    radius,
+                    gravity,
+                    atmosphere,
+                    orbit_distance,
+                    orbital_period: PlanetGen::calculate_orbital_period(
+                        orbit_distance,
+                        mass * PlanetGen::EARTH_MASS,
+                    ),
+                    surface_temperature,
+                    habitability,
+                    planet_type: PlanetType(moon_type.name.clone()),
+                    economic_type,
+                }
+            })
+            .collect::<Vec<_>>();
+        moons.sort_by(|a, b| a.orbit_distance.partial_cmp(&b.orbit_distance).unwrap());
+        moons
+    }
+
+    /// Generates the asteroid belts of a system, each orbiting a random star
+    /// at a distance from it. The orbits still have to be made stable.
+    pub fn generate_belts<R: Rng>(&self, gen: &mut R, stars: &[Star]) -> Vec<(usize, f64)> {
+        let mut belts = vec![];
+        while gen.gen::<f64>() < self.belt_chance {
+            let host = gen.gen_range(0, stars.len());
+            let distance = 500. + 2_500. * gen.gen::<f64>();
+            belts.push((host, distance));
+        }
+        belts
+    }
+
+    /// Creates an asteroid belt at the given orbit, belts are always mined.
+    pub fn belt<R: Rng>(gen: &mut R, orbit: Orbit, orbit_distance: f64) -> AsteroidBelt {
+        AsteroidBelt {
+            name: String::new(),
+            mass: 0.000_1 * 10f64.powf(2. * gen.gen::<f64>()),
+            orbit,
+            orbit_distance,
+            economic_type: PlanetEconomy::Extraction,
+        }
+    }
+
+    /// Generates the stations of a system. Every system has a hub station
+    /// holding its market, orbiting the most habitable planet or otherwise
+    /// the primary star. Refinery outposts serve every belt and some of the
+    /// gas giants without an economy of their own.
+    pub fn generate_stations<R: Rng>(
+        gen: &mut R,
+        planets: &[PlanetBuilder],
+        belts: &[AsteroidBelt],
+    ) -> Vec<Station> {
+        let mut hub = Body::Star(0);
+        let mut best = f64::NEG_INFINITY;
+        for (index, planet) in planets.iter().enumerate() {
+            let habitability = planet.habitability.unwrap();
+            if habitability > best {
+                hub = Body::Planet(index);
+                best = habitability;
+            }
+        }
+        let economies = [
+            PlanetEconomy::Industrial,
+            PlanetEconomy::HighTech,
+            PlanetEconomy::Military,
+        ];
+        let mut stations = vec![Station {
+            name: String::new(),
+            orbits: hub,
+            economic_type: gen.choose(&economies).unwrap().clone(),
+            market: true,
+        }];
+
+        let outposts = planets
+            .iter()
+            .enumerate()
+            .filter(|(_, planet)| {
+                planet.atmosphere.as_ref().unwrap().kind == AtmosphereType::Hydrogen
+                    && planet.economic_type == Some(PlanetEconomy::None)
+            })
+            .map(|(index, _)| Body::Planet(index))
+            .collect::<Vec<_>>();
+        for body in outposts {
+            if gen.gen::<f64>() < BodyGen::GAS_OUTPOST_CHANCE {
+                stations.push(BodyGen::outpost(body));
+            }
+        }
+        stations.extend((0..belts.len()).map(|index| BodyGen::outpost(Body::Belt(index))));
+        stations
+    }
+
+    /// Creates a refinery outpost orbiting the given body.
+    fn outpost(body: Body) -> Station {
+        Station {
+            name: String::new(),
+            orbits: body,
+            economic_type: PlanetEconomy::Refinary,
+            market: false,
+        }
+    }
+
+    /// Names the moons, belts and stations of a named system after the
+    /// bodies they orbit.
+    pub fn name(system: &mut System) {
+        for planet in &mut system.satelites {
+            for (index, moon) in planet.moons.iter_mut().enumerate() {
+                moon.name = format!("{} {}", planet.name, (b'a' + index as u8) as char);
+            }
+        }
+        let belt_count = system.belts.len();
+        for (index, belt) in system.belts.iter_mut().enumerate() {
+            belt.name = if belt_count == 1 {
+                format!("{} Belt", system.name)
+            } else {
+                format!("{} Belt {}", system.name, index + 1)
+            };
+        }
+        let names = system
+            .stations
+            .iter()
+            .map(|station| {
+                let kind = if station.market { "Station" } else { "Outpost" };
+                format!("{} {}", system.body_name(station.orbits), kind)
+            })
+            .collect::<Vec<_>>();
+        for (station, name) in system.stations.iter_mut().zip(names) {
+            station.name = name;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entities::Atmosphere;
+    use rand::{ChaChaRng, SeedableRng};
+
+    #[test]
+    fn test_stations() {
+        let mut rng = ChaChaRng::from_seed(&[1]);
+        let planets = vec![
+            PlanetBuilder::default()
+                .habitability(0.)
+                .atmosphere(Atmosphere {
+                    kind: AtmosphereType::Hydrogen,
+                    pressure: 1_000.,
+                })
+                .economic_type(PlanetEconomy::None)
+                .to_owned(),
+            PlanetBuilder::default()
+                .habitability(0.9)
+                .atmosphere(Atmosphere::none())
+                .economic_type(PlanetEconomy::Agriculture)
+                .to_owned(),
+        ];
+        let belts = vec![BodyGen::belt(&mut rng, Orbit::Star(0), 1_000.)];
+        let stations = BodyGen::generate_stations(&mut rng, &planets, &belts);
+
+        // Exactly one market, at the most habitable planet.
+        let markets = stations
+            .iter()
+            .filter(|station| station.market)
+            .collect::<Vec<_>>();
+        assert_eq!(markets.len(), 1);
+        assert_eq!(markets[0].orbits, Body::Planet(1));
+        assert!(stations
+            .iter()
+            .any(|station| station.orbits == Body::Belt(0)
+                && station.economic_type == PlanetEconomy::Refinary));
+
+        // Systems without planets still get a market.
+        let stations = BodyGen::generate_stations(&mut rng, &[], &[]);
+        assert_eq!(stations.len(), 1);
+        assert_eq!(stations[0].orbits, Body::Star(0));
+    }
+}
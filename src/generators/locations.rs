@@ -0,0 +1,205 @@
+use rand::{ChaChaRng, Rng, SeedableRng};
+use spade::rtree::RTree;
+use statrs::distribution::{Distribution, Normal};
+use std::{collections::HashSet, error::Error, f64::consts::PI, fmt, time::Instant};
+
+use config::{GalaxyShape, GameConfig};
+use utils::Point;
+
+/// Error returned when the galaxy is too dense to keep the systems at the
+/// minimum distance from each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrowdedGalaxy {
+    pub placed: usize,
+    pub min_distance: f64,
+}
+
+impl fmt::Display for CrowdedGalaxy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Galaxy is too dense, found room for only {} systems at the minimum distance {}",
+            self.placed, self.min_distance
+        )
+    }
+}
+
+impl Error for CrowdedGalaxy {}
+
+/// Used for generating the locations of all systems.
+pub struct LocationGen {}
+
+impl LocationGen {
+    /// Number of candidates tried for a system before giving up on placing it.
+    const MAX_ATTEMPTS: u32 = 100;
+
+    /// Create a new location generator.
+    pub fn new() -> LocationGen {
+        LocationGen {}
+    }
+
+    /// Place the systems according to the shape of the galaxy, rejecting
+    /// locations closer than the minimum distance to another system or with
+    /// the same hash as another system. Fails if a system cannot be placed.
+    pub fn generate(&self, config: &GameConfig) -> Result<Vec<Point>, CrowdedGalaxy> {
+        // Measure time for generation.
+        let now = Instant::now();
+
+        let seed: &[_] = &[config.map_seed];
+        let mut rng = ChaChaRng::from_seed(seed);
+        let spread = config.system_spread;
+        let count = config.number_of_systems as usize;
+        let min_distance = config.min_system_distance;
+
+        // Cluster centers are placed before any of the systems.
+        let centers = match config.shape {
+            GalaxyShape::Clustered(ref shape) => (0..shape.clusters)
+                .map(|_| gaussian(&mut rng, Point::origin(), spread))
+                .collect(),
+            _ => vec![],
+        };
+
+        let mut map = RTree::new();
+        let mut hashes = HashSet::new();
+        let mut locations = Vec::with_capacity(count);
+        while locations.len() < count {
+            let mut attempts = 0;
+            let location = loop {
+                if attempts >= LocationGen::MAX_ATTEMPTS {
+                    return Err(CrowdedGalaxy {
+                        placed: locations.len(),
+                        min_distance,
+                    });
+                }
+                attempts += 1;
+                let candidate = sample(&config.shape, spread, &centers, &mut rng);
+                let is_free = !hashes.contains(&candidate.hash())
+                    && map
+                        .nearest_neighbor(&candidate)
+                        .is_none_or(|neighbor: &Point| {
+                            neighbor.distance(&candidate) >= min_distance
+                        });
+                if is_free {
+                    break candidate;
+                }
+            };
+            hashes.insert(location.hash());
+            map.insert(location);
+            locations.push(location);
+        }
+
+        info!(
+            "Placed {} systems in a {:?} galaxy, taking {} ms",
+            count,
+            config.shape,
+            ((now.elapsed().as_secs() * 1_000) + u64::from(now.elapsed().subsec_millis()))
+        );
+
+        Ok(locations)
+    }
+}
+
+/// Samples the location of a single system from the shape.
+fn sample(shape: &GalaxyShape, spread: f64, centers: &[Point], rng: &mut ChaChaRng) -> Point {
+    match *shape {
+        GalaxyShape::Gaussian => gaussian(rng, Point::origin(), spread),
+        GalaxyShape::Elliptical(ref shape) => {
+            let (sin, cos) = shape.rotation.to_radians().sin_cos();
+            let point = gaussian(rng, Point::origin(), spread);
+            let (x, y) = (point.x, point.y * shape.ratio);
+            Point::new(x * cos - y * sin, x * sin + y * cos)
+        }
+        GalaxyShape::Spiral(ref shape) => {
+            if rng.gen::<f64>() < shape.bulge {
+                return gaussian(rng, Point::origin(), shape.bulge_size * spread);
+            }
+            // Arms extend to twice the spread, uniform distances along them
+            // make the disc denser towards the center.
+            let arm = rng.gen_range(0, shape.arms);
+            let radius = rng.gen::<f64>() * 2. * spread;
+            let angle =
+                f64::from(arm) * 2. * PI / f64::from(shape.arms) + shape.twist * radius / spread;
+            let center = Point::new(radius * angle.cos(), radius * angle.sin());
+            gaussian(rng, center, shape.arm_width * spread)
+        }
+        GalaxyShape::Ring(ref shape) => {
+            let angle = rng.gen::<f64>() * 2. * PI;
+            let center = Point::new(
+                shape.radius * spread * angle.cos(),
+                shape.radius * spread * angle.sin(),
+            );
+            gaussian(rng, center, shape.width * spread)
+        }
+        GalaxyShape::Clustered(ref shape) => {
+            let center = *rng.choose(centers).unwrap();
+            gaussian(rng, center, shape.cluster_spread * spread)
+        }
+    }
+}
+
+/// Samples a point from a normal distribution around the center.
+fn gaussian(rng: &mut ChaChaRng, center: Point, deviation: f64) -> Point {
+    let normal = Normal::new(0., deviation).unwrap();
+    Point::new(
+        center.x + normal.sample::<ChaChaRng>(rng),
+        center.y + normal.sample::<ChaChaRng>(rng),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{ClusteredShape, EllipticalShape, RingShape, SpiralShape};
+
+    #[test]
+    fn test_shapes() {
+        let shapes = vec![
+            GalaxyShape::Gaussian,
+            GalaxyShape::Elliptical(EllipticalShape::default()),
+            GalaxyShape::Spiral(SpiralShape::default()),
+            GalaxyShape::Ring(RingShape::default()),
+            GalaxyShape::Clustered(ClusteredShape::default()),
+        ];
+        for shape in shapes {
+            let config = GameConfig {
+                number_of_systems: 500,
+                system_spread: 10.,
+                min_system_distance: 0.1,
+                shape,
+                ..GameConfig::default()
+            };
+            let locations = LocationGen::new().generate(&config).unwrap();
+            assert_eq!(locations.len(), 500);
+            assert_eq!(locations, LocationGen::new().generate(&config).unwrap());
+
+            // The center of the ring should be empty.
+            if let GalaxyShape::Ring(_) = config.shape {
+                assert!(locations
+                    .iter()
+                    .all(|location| location.distance(&Point::origin()) > 5.));
+            }
+        }
+    }
+
+    #[test]
+    fn test_crowded_galaxy() {
+        // Systems sharing the hash of another system are rejected as well.
+        let config = GameConfig {
+            number_of_systems: 10,
+            number_of_sectors: 2,
+            system_spread: 1e-9,
+            ..GameConfig::default()
+        };
+        assert!(LocationGen::new().generate(&config).is_err());
+
+        let config = GameConfig {
+            number_of_systems: 100,
+            number_of_sectors: 2,
+            system_spread: 1.,
+            min_system_distance: 10.,
+            ..GameConfig::default()
+        };
+        let error = LocationGen::new().generate(&config).unwrap_err();
+        assert!(error.placed < 100);
+    }
+}
@@ -1,15 +1,14 @@
-use rand::{ChaChaRng, SeedableRng};
 use rayon::prelude::*;
-use statrs::distribution::{Distribution, Normal};
 use std::time::Instant;
 
 use config::GameConfig;
 use entities::{
     Galaxy, SystemBuilder, {Planet, PlanetBuilder},
 };
-use resources::{fetch_resource, AstronomicalNamesResource};
-use utils::Point;
+use resources::{fetch_resource, AstronomicalNamesResource, FactionNamesResource};
 
+pub mod bodies;
+pub mod locations;
 pub mod names;
 pub mod planets;
 pub mod sectors;
@@ -17,62 +16,43 @@ pub mod stars;
 pub mod systems;
 
 /// Generate a galaxy with systems etc, will use the provided config to guide
-/// the generation.
-pub fn generate_galaxy(config: &GameConfig) -> Galaxy {
-    let new_seed: &[_] = &[config.map_seed as u32];
-    let mut rng = ChaChaRng::from_seed(new_seed);
-
+/// the generation. Fails if the galaxy is too dense to fit all systems.
+pub fn generate_galaxy(config: &GameConfig) -> Result<Galaxy, locations::CrowdedGalaxy> {
     // Measure time for generation.
     let now = Instant::now();
 
-    // Clusters are spaced uniformly, systems gaussian.
-    let loc_x = Normal::new(0., config.system_spread).unwrap();
-    let loc_y = Normal::new(0., config.system_spread).unwrap();
-
     // Generate system locations.
-    let mut locations = vec![];
-    for _ in 0..config.number_of_systems {
-        locations.push(Point::new(
-            loc_x.sample::<ChaChaRng>(&mut rng),
-            loc_y.sample::<ChaChaRng>(&mut rng),
-        ))
-    }
+    let location_gen = locations::LocationGen::new();
+    let locations = location_gen.generate(config)?;
 
-    // Create name generator to be shared mutably.
+    // Create name generator to be shared mutably. The astronomical names
+    // provide the suffixes of all names, and the names of any faction missing
+    // from the faction naming styles.
     let mut name_gen = names::NameGen::from_seed(config.map_seed);
     name_gen.train(fetch_resource::<AstronomicalNamesResource>().unwrap());
+    name_gen.train_factions(fetch_resource::<FactionNamesResource>().unwrap());
 
     // Generate sectors
     let sector_gen = sectors::SectorGen::new();
     let sectors = sector_gen.generate(config, locations);
     // Create System generator.
-    let system_gen = systems::SystemGen::new();
+    let system_gen = systems::SystemGen::new(config);
 
-    // Generate systems for each cluster in parallel.
-    // Fold will generate one vector per thread (per cluster), reduce will
-    // combine them to the final result.
+    // Generate systems for each cluster in parallel, collecting them in sector
+    // order regardless of how the work is split between threads.
+    let system_gen = &system_gen;
     let mut builders = sectors
         .par_iter()
-        .fold(
-            Vec::<(SystemBuilder, Vec<PlanetBuilder>)>::new,
-            |mut systems: Vec<(SystemBuilder, Vec<PlanetBuilder>)>, sector| {
-                for location in &sector.system_locations {
-                    // Generate system
-                    systems.push(system_gen.generate(*location, sector.faction.clone()));
-                }
-                systems
-            },
-        )
-        .reduce(
-            Vec::<(SystemBuilder, Vec<PlanetBuilder>)>::new,
-            |mut systems, subsystems| {
-                systems.extend(subsystems);
-                systems
-            },
-        );
+        .flat_map(|sector| {
+            sector
+                .system_locations
+                .par_iter()
+                .map(move |location| system_gen.generate(*location, sector.faction.clone()))
+        })
+        .collect::<Vec<(SystemBuilder, Vec<PlanetBuilder>)>>();
 
     // Sort to ensure that naming etc, will be deterministic.
-    builders.sort_by_key(|&(ref system_builder, _)| system_builder.location.unwrap().hash());
+    builders.sort_by_key(|(system_builder, _)| system_builder.location.unwrap().hash());
 
     let systems = builders
         .into_iter()
@@ -80,30 +60,76 @@ pub fn generate_galaxy(config: &GameConfig) -> Galaxy {
             let hash = system_builder.location.unwrap().hash();
             name_gen.reseed(hash as u32);
 
-            let (system_name, planet_names) = name_gen.generate(planet_builders.len());
+            let faction = system_builder.faction.clone().unwrap();
+            let (system_name, planet_names) = name_gen.generate(&faction, planet_builders.len());
 
             let planets: Vec<Planet> = planet_builders
                 .into_iter()
-                .zip(planet_names.into_iter())
+                .zip(planet_names)
                 .map(|(mut builder, name)| builder.name(name).build().unwrap())
                 .collect();
 
-            system_builder
+            let mut system = system_builder
                 .name(system_name)
                 .satelites(planets)
                 .build()
-                .unwrap()
+                .unwrap();
+            bodies::BodyGen::name(&mut system);
+            system
         })
         .collect::<Vec<_>>();
 
     info!(
-        "Generated new galaxy containing: {} systems and {} planets taking {} ms",
+        "Generated new galaxy containing: {} systems, {} planets, {} moons, {} belts and {} stations taking {} ms",
         systems.len(),
         systems
             .iter()
             .fold(0, |acc, sys| acc + sys.satelites.len(),),
+        systems.iter().fold(0, |acc, sys| acc
+            + sys
+                .satelites
+                .iter()
+                .fold(0, |acc, planet| acc + planet.moons.len())),
+        systems.iter().fold(0, |acc, sys| acc + sys.belts.len()),
+        systems.iter().fold(0, |acc, sys| acc + sys.stations.len()),
         ((now.elapsed().as_secs() * 1_000) + u64::from(now.elapsed().subsec_millis()))
     );
 
-    Galaxy::new(sectors, systems)
+    Ok(Galaxy::new(sectors, systems))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_system_count() {
+        // Dense enough that unchecked sampling would place systems too close.
+        let config = GameConfig {
+            number_of_systems: 2_000,
+            system_spread: 5.,
+            number_of_sectors: 10,
+            min_system_distance: 0.05,
+            ..GameConfig::default()
+        };
+        let galaxy = generate_galaxy(&config).unwrap();
+        assert_eq!(galaxy.systems().count(), 2_000);
+
+        let hashes = galaxy
+            .systems()
+            .map(|system| system.location.hash())
+            .collect::<HashSet<_>>();
+        assert_eq!(hashes.len(), 2_000);
+
+        let locations = galaxy
+            .systems()
+            .map(|system| system.location)
+            .collect::<Vec<_>>();
+        for (index, location) in locations.iter().enumerate() {
+            assert!(locations[index + 1..]
+                .iter()
+                .all(|other| location.distance(other) >= config.min_system_distance));
+        }
+    }
 }
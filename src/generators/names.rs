@@ -1,13 +1,148 @@
+use entities::Faction;
 use inflector::Inflector;
 use rand::{ChaChaRng, Rng, SeedableRng};
-use resources::AstronomicalNamesResource;
+use resources::{AstronomicalNamesResource, FactionNamesResource};
 use statrs::distribution::{Categorical, Distribution};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+/// Character marking the start and end of a name in the Markov model.
+const BOUNDARY: char = '\0';
+
+/// Markov model over the characters of names, backing off to shorter contexts
+/// for contexts never seen in training.
+struct MarkovModel {
+    order: usize,
+    prior: f64,
+    support: Vec<char>,
+    counts: HashMap<Vec<char>, Vec<f64>>,
+}
+
+impl MarkovModel {
+    /// Creates an untrained model using contexts of up to order characters,
+    /// where prior is the count given to every character in a seen context.
+    fn new(order: usize, prior: f64) -> Self {
+        MarkovModel {
+            order,
+            prior,
+            support: vec![BOUNDARY],
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Trains the model on the given names.
+    fn train(&mut self, names: &[String]) {
+        let mut support = names
+            .iter()
+            .flat_map(|name| name.chars())
+            .collect::<Vec<_>>();
+        support.push(BOUNDARY);
+        support.sort_unstable();
+        support.dedup();
+        self.support = support;
+        self.counts.clear();
+
+        for name in names {
+            let sequence = (0..self.order)
+                .map(|_| BOUNDARY)
+                .chain(name.chars())
+                .chain(Some(BOUNDARY))
+                .collect::<Vec<_>>();
+            for i in self.order..sequence.len() {
+                let event = self.support.binary_search(&sequence[i]).unwrap();
+                let context = &sequence[i - self.order..i];
+                for j in 0..=self.order {
+                    let (prior, size) = (self.prior, self.support.len());
+                    self.counts
+                        .entry(context[j..].to_vec())
+                        .or_insert_with(|| vec![prior; size])[event] += 1.;
+                }
+            }
+        }
+    }
+
+    /// Samples the next character following the given context.
+    fn sample<R: Rng>(&self, rng: &mut R, context: &[char]) -> char {
+        let mut context = &context[context.len().saturating_sub(self.order)..];
+        while !self.counts.contains_key(context) && !context.is_empty() {
+            context = &context[1..];
+        }
+        let counts = match self.counts.get(context) {
+            Some(counts) => counts,
+            None => return BOUNDARY,
+        };
+
+        let mut sample = rng.gen::<f64>() * counts.iter().sum::<f64>();
+        for (symbol, count) in self.support.iter().zip(counts) {
+            if sample < *count {
+                return *symbol;
+            }
+            sample -= count;
+        }
+        BOUNDARY
+    }
+
+    /// Generates a new name of at most max_length characters.
+    fn generate<R: Rng>(&self, rng: &mut R, max_length: usize) -> String {
+        let mut sequence = vec![BOUNDARY; self.order];
+        while sequence.len() < self.order + max_length {
+            match self.sample(rng, &sequence) {
+                BOUNDARY => break,
+                symbol => sequence.push(symbol),
+            }
+        }
+        sequence[self.order..].iter().collect()
+    }
+}
+
+/// Markov model which is trained on first use, as it is only needed once names
+/// have to be generated rather than taken from a list.
+struct LazyModel {
+    training_names: Vec<String>,
+    model: Option<MarkovModel>,
+}
+
+impl LazyModel {
+    /// Creates a model to be trained on the given names.
+    fn new(mut training_names: Vec<String>) -> Self {
+        training_names.sort_unstable();
+        training_names.dedup();
+        LazyModel {
+            training_names,
+            model: None,
+        }
+    }
+
+    /// Returns true if the name is part of the training names.
+    fn is_training_name(&self, name: &str) -> bool {
+        self.training_names
+            .binary_search_by(|training_name| training_name.as_str().cmp(name))
+            .is_ok()
+    }
+
+    /// Trains the model if it has not been trained yet.
+    fn trained(&mut self) -> &Self {
+        if self.model.is_none() {
+            let mut model = MarkovModel::new(NameGen::MODEL_ORDER, NameGen::MODEL_PRIOR);
+            model.train(&self.training_names);
+            self.model = Some(model);
+        }
+        self
+    }
+}
 
 /// Name generator which generates based on names given in training data.
+/// Factions with their own naming style get names generated from models trained
+/// on their corpora, all other names are taken from a shared list.
 pub struct NameGen {
     rng: ChaChaRng,
     base_names: Vec<String>,
-    cache: Vec<String>,
+    used_names: HashSet<String>,
+    model: LazyModel,
+    faction_models: HashMap<Faction, LazyModel>,
+    cache: HashMap<Faction, Vec<String>>,
     greek_suffix: Vec<String>,
     roman_suffix: Vec<String>,
     decorator_suffix: Vec<String>,
@@ -15,6 +150,14 @@ pub struct NameGen {
 }
 
 impl NameGen {
+    const MODEL_ORDER: usize = 3;
+    const MODEL_PRIOR: f64 = 0.001;
+    const MIN_LENGTH: usize = 4;
+    const MAX_LENGTH: usize = 8;
+    /// Number of names sampled from the model before giving up on finding an
+    /// unused one and numbering it instead.
+    const MAX_ATTEMPTS: usize = 100;
+
     /// Creates a new NameGen with the given seed.
     pub fn from_seed(seed: u32) -> NameGen {
         // Create and initialize random generator using seed.
@@ -23,8 +166,11 @@ impl NameGen {
 
         NameGen {
             rng,
-            cache: vec![],
+            cache: HashMap::new(),
             base_names: vec![],
+            used_names: HashSet::new(),
+            model: LazyModel::new(vec![]),
+            faction_models: HashMap::new(),
             greek_suffix: vec![],
             roman_suffix: vec![],
             decorator_suffix: vec![],
@@ -39,8 +185,12 @@ impl NameGen {
         self.rng.reseed(new_seed);
     }
 
-    /// Trains the underlying model using the given resouce.
+    /// Trains the shared model, used by factions without a naming style, and
+    /// loads the suffixes used by all factions from the given resource.
     pub fn train(&mut self, data: AstronomicalNamesResource) {
+        self.model = LazyModel::new(data.names.clone());
+        self.used_names.clear();
+        self.cache.clear();
         self.base_names = data.names;
         self.scientific_names = data.scientific_names;
 
@@ -53,37 +203,111 @@ impl NameGen {
         self.decorator_suffix = data.decorators;
     }
 
-    /// Generates a new main name using base names and suffixes.
-    fn generate_name(&mut self) -> Option<String> {
-        if self.cache.is_empty() {
-            if let Some(base_name) = self.base_names.pop() {
-                let suffix_probs = Categorical::new(&[80., 8., 4., 8.]).unwrap();
+    /// Trains one model per faction on the corpora listed for it in the given
+    /// resource, giving each faction its own naming style.
+    pub fn train_factions(&mut self, data: FactionNamesResource) {
+        self.faction_models.clear();
+        for (faction, corpora) in data.factions {
+            let mut names = vec![];
+            for corpus in &corpora {
+                match data.corpora.get(corpus) {
+                    Some(corpus_names) => names.extend(corpus_names.iter().cloned()),
+                    None => warn!("Unknown naming corpus {} for {}", corpus, faction),
+                }
+            }
+            if !names.is_empty() {
+                self.faction_models.insert(faction, LazyModel::new(names));
+            }
+        }
+    }
 
-                let suffixes = match suffix_probs.sample(&mut self.rng) as usize {
-                    1 => self.greek_suffix.clone(),
-                    2 => self.roman_suffix.clone(),
-                    3 => self.decorator_suffix.clone(),
-                    _ => vec![],
-                };
+    /// Returns the next base name from the training data which does not look
+    /// the same as any name already used.
+    fn next_base_name(&mut self) -> Option<String> {
+        while let Some(base_name) = self.base_names.pop() {
+            if self.used_names.insert(base_name.to_title_case()) {
+                return Some(base_name);
+            }
+        }
+        None
+    }
 
-                self.cache.push(base_name.to_title_case());
-                for suffix in suffixes {
-                    self.cache
-                        .push(format!("{} {}", base_name.to_title_case(), suffix));
-                }
-            } else if !self.scientific_names.is_empty() {
-                self.cache.append(&mut self.scientific_names);
+    /// Generates a new base name which has not been used before, from the model
+    /// of the faction if it has one and otherwise from the shared model.
+    fn generate_base_name(&mut self, faction: &Faction) -> String {
+        let lazy_model = match self.faction_models.get_mut(faction) {
+            Some(faction_model) => faction_model,
+            None => &mut self.model,
+        }
+        .trained();
+        let model = lazy_model.model.as_ref().unwrap();
+
+        for _ in 0..NameGen::MAX_ATTEMPTS {
+            let name = model.generate(&mut self.rng, NameGen::MAX_LENGTH + 1);
+            let length = name.chars().count();
+            if (NameGen::MIN_LENGTH..=NameGen::MAX_LENGTH).contains(&length)
+                && !lazy_model.is_training_name(&name)
+                && self.used_names.insert(name.to_title_case())
+            {
+                return name;
             }
         }
-        self.cache.pop()
+
+        // Model is exhausted, number the names instead.
+        let name = model.generate(&mut self.rng, NameGen::MAX_LENGTH);
+        (2..)
+            .map(|number| format!("{} {}", name, number))
+            .find(|numbered| self.used_names.insert(numbered.to_title_case()))
+            .unwrap()
     }
 
-    /// Attempts to generate a new name main name and several secondary names from the model.
-    pub fn generate(&mut self, subcount: usize) -> (String, Vec<String>) {
-        let main_name = self.generate_name().unwrap_or_else(|| {
-            error!("Failed to generate main system name");
-            panic!()
-        });
+    /// Returns the next batch of names, a base name along with any suffixed
+    /// variations of it. For factions without a naming style names are taken
+    /// from the training data until it runs out, after which they are generated
+    /// by the model.
+    fn next_names(&mut self, faction: &Faction) -> Vec<String> {
+        let has_style = self.faction_models.contains_key(faction);
+        if !has_style && self.base_names.is_empty() && !self.scientific_names.is_empty() {
+            return mem::take(&mut self.scientific_names);
+        }
+
+        let base_name = match if has_style {
+            None
+        } else {
+            self.next_base_name()
+        } {
+            Some(base_name) => base_name,
+            None => self.generate_base_name(faction),
+        };
+        let suffix_probs = Categorical::new(&[80., 8., 4., 8.]).unwrap();
+
+        let suffixes = match suffix_probs.sample(&mut self.rng) as usize {
+            1 => self.greek_suffix.clone(),
+            2 => self.roman_suffix.clone(),
+            3 => self.decorator_suffix.clone(),
+            _ => vec![],
+        };
+
+        let mut names = vec![base_name.to_title_case()];
+        for suffix in suffixes {
+            names.push(format!("{} {}", base_name.to_title_case(), suffix));
+        }
+        names
+    }
+
+    /// Generates a new main name for the faction using base names and suffixes.
+    fn generate_name(&mut self, faction: &Faction) -> String {
+        if self.cache.get(faction).is_none_or(|cache| cache.is_empty()) {
+            let names = self.next_names(faction);
+            self.cache.insert(faction.clone(), names);
+        }
+        self.cache.get_mut(faction).unwrap().pop().unwrap()
+    }
+
+    /// Attempts to generate a new name main name and several secondary names
+    /// from the model, in the naming style of the given faction.
+    pub fn generate(&mut self, faction: &Faction, subcount: usize) -> (String, Vec<String>) {
+        let main_name = self.generate_name(faction);
 
         let subname_type = Categorical::new(&[10., 40., 40.]).unwrap();
 
@@ -95,21 +319,25 @@ impl NameGen {
         match subname_type.sample(&mut self.rng) as usize {
             0 => {
                 for _ in 1..subcount {
-                    let sub_name = self
-                        .generate_name()
-                        .unwrap_or_else(|| String::from("Unnamed"));
+                    let sub_name = self.generate_name(faction);
                     sub_names.push(sub_name);
                 }
-                sub_names.push(main_name.clone());
+                if subcount > 0 {
+                    sub_names.push(main_name.clone());
+                }
             }
             1 => {
-                for index in 1..subcount {
+                for index in 1..=subcount {
                     sub_names.push(format!("{} A-{}", main_name, index));
                 }
             }
             _ => {
-                for character in alphabet.iter().take(subcount) {
-                    sub_names.push(format!("{} {}", main_name, character));
+                // Fall back to numbers once the letters run out.
+                for index in 0..subcount {
+                    sub_names.push(match alphabet.get(index) {
+                        Some(character) => format!("{} {}", main_name, character),
+                        None => format!("{} {}", main_name, index + 1),
+                    });
                 }
             }
         };
@@ -118,3 +346,92 @@ impl NameGen {
         (main_name, sub_names)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resources::fetch_resource;
+
+    #[test]
+    fn test_generate_beyond_training_data() {
+        let names = [
+            "abakov", "achilles", "aeneas", "balakin", "castor", "demidov",
+        ];
+        let mut name_gen = NameGen::from_seed(42);
+        name_gen.train(AstronomicalNamesResource {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            scientific_names: vec![String::from("CZ-1")],
+            greek: vec![],
+            roman: vec![],
+            decorators: vec![],
+        });
+
+        let mut main_names = HashSet::new();
+        for seed in 0..200 {
+            name_gen.reseed(seed);
+            let (main_name, sub_names) = name_gen.generate(&Faction::Independent, 3);
+            assert!(main_names.insert(main_name));
+            assert_eq!(sub_names.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_faction_styles() {
+        let corpus = [
+            "achilles", "aeneas", "aeolus", "agrius", "alcestis", "amphion",
+        ];
+        let mut name_gen = NameGen::from_seed(42);
+        name_gen.train(AstronomicalNamesResource {
+            names: vec![String::from("levich")],
+            scientific_names: vec![],
+            greek: vec![],
+            roman: vec![],
+            decorators: vec![],
+        });
+        name_gen.train_factions(FactionNamesResource {
+            factions: vec![(Faction::Empire, vec![String::from("greek")])]
+                .into_iter()
+                .collect(),
+            corpora: vec![(
+                String::from("greek"),
+                corpus.iter().map(|name| name.to_string()).collect(),
+            )]
+            .into_iter()
+            .collect(),
+        });
+
+        // Styled factions get new names from their corpus, others the shared list.
+        for _ in 0..10 {
+            let (main_name, _) = name_gen.generate(&Faction::Empire, 1);
+            let base_name = main_name.split(' ').next().unwrap().to_lowercase();
+            assert!(!corpus.contains(&base_name.as_str()));
+            assert_ne!(base_name, "levich");
+        }
+        assert_eq!(name_gen.generate(&Faction::Cartel, 1).0, "Levich");
+    }
+
+    #[test]
+    fn test_unstyled_faction_fallback() {
+        // Every faction has a style in the shipped resource, remove one to
+        // check that it falls back to the astronomical names.
+        let astronomical = fetch_resource::<AstronomicalNamesResource>().unwrap();
+        let base_names = astronomical
+            .names
+            .iter()
+            .map(|name| name.to_title_case())
+            .collect::<Vec<_>>();
+        let mut faction_names = fetch_resource::<FactionNamesResource>().unwrap();
+        assert!(faction_names.factions.remove(&Faction::Cartel).is_some());
+
+        let mut name_gen = NameGen::from_seed(42);
+        name_gen.train(astronomical);
+        name_gen.train_factions(faction_names);
+
+        for _ in 0..10 {
+            let (main_name, _) = name_gen.generate(&Faction::Cartel, 1);
+            assert!(base_names
+                .iter()
+                .any(|base_name| main_name.starts_with(base_name.as_str())));
+        }
+    }
+}
@@ -2,92 +2,259 @@ use rand::Rng;
 use statrs::distribution::{Distribution, Exponential, Gamma};
 use std::f64::consts::PI;
 
+use config::PlanetConfig;
 use entities::{
-    Star, {PlanetBuilder, PlanetEconomy, PlanetType},
+    Atmosphere, AtmosphereType, Star, {PlanetBuilder, PlanetEconomy},
 };
+use resources::{fetch_resource, PlanetTypeInfo, PlanetTypesResource};
 
 /// Basic non deterministic name generator for generating new Planets which
 /// are similar to the trained data provided.
 pub struct PlanetGen {
     mass_gen: Exponential,
     orbit_dist_gen: Gamma,
+    min_orbit_distance: f64,
+    types: Vec<PlanetTypeInfo>,
 }
 
 impl PlanetGen {
-    /// Minimal distance to star.
-    // TODO: Move to config.
-    const MIN_DIST: f64 = 500.;
+    /// Distance from the Earth to the Sun, in light seconds.
+    const ASTRONOMICAL_UNIT: f64 = 499.;
+    /// Mass of the Earth, in solar masses.
+    pub const EARTH_MASS: f64 = 3.003e-6;
+    /// Surface temperature of the Earth, in Kelvin.
+    const EARTH_TEMPERATURE: f64 = 288.;
 
-    /// Create a new Planet generator which loads the star resources needed.
-    pub fn new() -> Self {
-        let mass_gen = Exponential::new(1. / 3.).unwrap();
+    /// Create a new Planet generator which loads the planet type resources
+    /// needed.
+    pub fn new(config: &PlanetConfig) -> Self {
+        let mass_gen = Exponential::new(1. / config.mean_mass).unwrap();
         let orbit_dist_gen = Gamma::new(0.28, 0.17).unwrap();
         PlanetGen {
             mass_gen,
             orbit_dist_gen,
+            min_orbit_distance: config.min_orbit_distance,
+            types: fetch_resource::<PlanetTypesResource>().unwrap().types,
         }
     }
 
     /// Calculate planet surface temperature from star luminosity and distance
     /// to it. Uses the Bond albedo for the Earth.
-    pub fn calculate_surface_temperature(orbit_distance: f64, star: &Star) -> f64 {
-        (star.luminosity * 3.846 * 10f64.powi(26) * (1. - 0.29)
+    pub fn calculate_surface_temperature(orbit_distance: f64, luminosity: f64) -> f64 {
+        (luminosity * 3.846 * 10f64.powi(26) * (1. - 0.29)
             / (16. * PI * (299_692_458. * orbit_distance).powi(2) * 5.670_373 * 10f64.powi(-8)))
         .powf(0.25)
     }
 
-    /// Predict the planet type based on surface_temperature and mass.
-    pub fn predict_type<R: Rng>(rng: &mut R, surface_temperature: f64, mass: f64) -> PlanetType {
-        // Based on trained decision tree with modifications to allow for
-        // Earth-like planets at a higher rate.
+    /// Calculate planet radius in Earth radii from its mass and composition,
+    /// which depends on the planet type.
+    pub fn calculate_radius(mass: f64, kind: &PlanetTypeInfo) -> f64 {
+        (kind.radius_factor * mass.powf(kind.radius_exponent)).min(kind.max_radius)
+    }
+
+    /// Calculate surface gravity in g from mass and radius in Earth units.
+    pub fn calculate_gravity(mass: f64, radius: f64) -> f64 {
+        mass / radius.powi(2)
+    }
+
+    /// Calculate the orbital period in days using Kepler's third law, from
+    /// the orbit distance and the mass orbited in solar masses.
+    pub fn calculate_orbital_period(orbit_distance: f64, central_mass: f64) -> f64 {
+        365.25 * ((orbit_distance / PlanetGen::ASTRONOMICAL_UNIT).powi(3) / central_mass).sqrt()
+    }
+
+    /// Predict the atmosphere based on type, mass, radius and temperature.
+    /// Small and hot planets can not hold on to an atmosphere, larger ones
+    /// hold on to denser atmospheres.
+    pub fn predict_atmosphere<R: Rng>(
+        rng: &mut R,
+        kind: &PlanetTypeInfo,
+        mass: f64,
+        radius: f64,
+        surface_temperature: f64,
+    ) -> Atmosphere {
+        // Escape velocity relative to the speed of gas molecules, both
+        // relative to the Earth.
+        let retention =
+            (mass / radius).sqrt() / (surface_temperature / PlanetGen::EARTH_TEMPERATURE).sqrt();
+        if !kind.giant && retention < 0.4 {
+            return Atmosphere::none();
+        }
+
         let random_val: f64 = rng.gen();
-        match (surface_temperature, mass) {
-            (_, y) if y >= 5.185 => PlanetType::GasGiant,
-            (x, y) if x < 124.5 && y < 5.185 => PlanetType::Icy,
-            (x, _) if x > 280. && x < 310. => PlanetType::Earth,
-            (x, _) if x >= 124.5 && random_val < 0.8 => PlanetType::Rocky,
-            (x, _) if x >= 124.5 && random_val >= 0.8 => PlanetType::Metal,
-            _ => PlanetType::Rocky,
+        let chance = match pick(&kind.atmospheres, |chance| chance.chance, random_val) {
+            Some(chance) if chance.kind != AtmosphereType::None => chance,
+            _ => return Atmosphere::none(),
+        };
+        // Pressures are distributed uniformly on a logarithmic scale.
+        let pressure = chance.min_pressure
+            * (chance.max_pressure / chance.min_pressure).powf(rng.gen::<f64>());
+        Atmosphere {
+            kind: chance.kind.clone(),
+            pressure: if kind.giant {
+                pressure
+            } else {
+                pressure * retention.powi(2)
+            },
         }
     }
 
-    /// Predict the planet economy type based on type.
-    pub fn predict_economy<R: Rng>(rng: &mut R, kind: &PlanetType) -> PlanetEconomy {
-        // Based on trained decision tree with modifications to allow for
-        // Earth-like planets at a higher rate.
+    /// Calculate how suitable a planet is for human life, 1 for the
+    /// temperature, gravity and atmosphere of the Earth. Planets without a
+    /// breathable atmosphere can only be lived on in habitats.
+    pub fn calculate_habitability(
+        surface_temperature: f64,
+        gravity: f64,
+        atmosphere: &Atmosphere,
+    ) -> f64 {
+        let atmosphere_factor = match atmosphere.kind {
+            AtmosphereType::Breathable => 1.,
+            AtmosphereType::Nitrogen | AtmosphereType::CarbonDioxide => 0.3,
+            AtmosphereType::Methane => 0.1,
+            AtmosphereType::None | AtmosphereType::Hydrogen => 0.,
+        };
+        let temperature_factor =
+            gaussian((surface_temperature - PlanetGen::EARTH_TEMPERATURE) / 30.);
+        let gravity_factor = gaussian(gravity.ln() / 0.5);
+        let pressure_factor = gaussian(atmosphere.pressure.max(0.001).ln() / 1.5);
+        atmosphere_factor * temperature_factor * gravity_factor * pressure_factor
+    }
+
+    /// Predict the planet type based on surface_temperature and mass, picking
+    /// one of the matching types by weight. Falls back to the first type if
+    /// none of the types match.
+    pub fn predict_type<R: Rng>(
+        &self,
+        rng: &mut R,
+        surface_temperature: f64,
+        mass: f64,
+    ) -> &PlanetTypeInfo {
         let random_val: f64 = rng.gen();
-        match *kind {
-            PlanetType::Icy if random_val < 0.2 => PlanetEconomy::Extraction,
-            PlanetType::Icy if random_val < 0.4 => PlanetEconomy::Refinary,
-            PlanetType::Icy if random_val < 0.7 => PlanetEconomy::HighTech,
-            PlanetType::Icy if random_val < 0.8 => PlanetEconomy::Military,
-            PlanetType::Icy => PlanetEconomy::Industrial,
-            PlanetType::Rocky if random_val < 0.2 => PlanetEconomy::Extraction,
-            PlanetType::Rocky if random_val < 0.4 => PlanetEconomy::Refinary,
-            PlanetType::Rocky if random_val < 0.7 => PlanetEconomy::HighTech,
-            PlanetType::Rocky if random_val < 0.9 => PlanetEconomy::Military,
-            PlanetType::Rocky => PlanetEconomy::Industrial,
-            PlanetType::Earth => PlanetEconomy::Agriculture,
-            PlanetType::Metal => PlanetEconomy::Extraction,
-            _ => PlanetEconomy::None,
-        }
+        let candidates = self
+            .types
+            .iter()
+            .filter(|kind| kind.matches(mass, surface_temperature))
+            .collect::<Vec<_>>();
+        let total = candidates.iter().map(|kind| kind.weight).sum::<f64>();
+        pick(&candidates, |kind| kind.weight / total, random_val)
+            .cloned()
+            .unwrap_or(&self.types[0])
+    }
+
+    /// Predict the planet economy type based on the chances of the type.
+    pub fn predict_economy<R: Rng>(rng: &mut R, kind: &PlanetTypeInfo) -> PlanetEconomy {
+        let random_val: f64 = rng.gen();
+        pick(&kind.economies, |chance| chance.chance, random_val)
+            .map_or(PlanetEconomy::None, |chance| chance.economy.clone())
     }
 
     /// Generates a new PlanetBuilder from the _distribution_ using the provided random
     /// generator. Sets the fields which are independent on the context.
-    pub fn generate<R: Rng>(&self, gen: &mut R) -> Option<PlanetBuilder> {
+    pub fn generate<R: Rng>(&self, gen: &mut R, star: &Star) -> Option<PlanetBuilder> {
         let mass = self.mass_gen.sample(gen);
 
+        // Keep planets well outside of giant stars.
+        let min_orbit_distance = self
+            .min_orbit_distance
+            .max(2. * star.radius * Star::SOLAR_RADIUS);
+
         // Magic constant, needed to scale back since scaling needed to fit gamma.
-        let orbit_distance = PlanetGen::MIN_DIST + 1000. * self.orbit_dist_gen.sample(gen);
+        let orbit_distance = min_orbit_distance + 1000. * self.orbit_dist_gen.sample(gen);
 
-        // TODO: Make something a bit more accurate regarding planet type and gravity.
         Some(
             PlanetBuilder::default()
                 .mass(mass)
                 .orbit_distance(orbit_distance)
-                .gravity(mass)
                 .to_owned(),
         )
     }
 }
+
+/// Picks the first item whose cumulative chance exceeds the random value,
+/// falling back to the last item.
+fn pick<T, F: Fn(&T) -> f64>(items: &[T], chance: F, random_val: f64) -> Option<&T> {
+    let mut cumulative = 0.;
+    items
+        .iter()
+        .find(|item| {
+            cumulative += chance(item);
+            random_val < cumulative
+        })
+        .or_else(|| items.last())
+}
+
+/// Unnormalized standard normal density, 1 at zero.
+fn gaussian(deviations: f64) -> f64 {
+    (-deviations.powi(2) / 2.).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{ChaChaRng, SeedableRng};
+
+    #[test]
+    fn test_earth_analogue() {
+        let planet_gen = PlanetGen::new(&PlanetConfig::default());
+        let earth = planet_gen
+            .types
+            .iter()
+            .find(|kind| kind.name == "Earth")
+            .unwrap();
+        let radius = PlanetGen::calculate_radius(1., earth);
+        let gravity = PlanetGen::calculate_gravity(1., radius);
+        assert_eq!((radius, gravity), (1., 1.));
+
+        let period = PlanetGen::calculate_orbital_period(499., 1.);
+        assert!((period - 365.25).abs() < 1e-9);
+
+        let atmosphere = Atmosphere {
+            kind: AtmosphereType::Breathable,
+            pressure: 1.,
+        };
+        assert_eq!(
+            PlanetGen::calculate_habitability(288., gravity, &atmosphere),
+            1.
+        );
+        assert!(PlanetGen::calculate_habitability(288., 3., &atmosphere) < 0.2);
+        assert_eq!(
+            PlanetGen::calculate_habitability(288., gravity, &Atmosphere::none()),
+            0.
+        );
+
+        // The Moon is too small to hold on to an atmosphere.
+        let mut rng = ChaChaRng::from_seed(&[1]);
+        let atmosphere = PlanetGen::predict_atmosphere(&mut rng, earth, 0.012, 0.27, 250.);
+        assert_eq!(atmosphere, Atmosphere::none());
+    }
+
+    #[test]
+    fn test_planet_types() {
+        let planet_gen = PlanetGen::new(&PlanetConfig::default());
+        for kind in &planet_gen.types {
+            let atmospheres = kind.atmospheres.iter().map(|chance| chance.chance);
+            let economies = kind.economies.iter().map(|chance| chance.chance);
+            assert!(
+                (atmospheres.sum::<f64>() - 1.).abs() < 1e-9,
+                "{}",
+                kind.name
+            );
+            assert!((economies.sum::<f64>() - 1.).abs() < 1e-9, "{}", kind.name);
+        }
+
+        // Every planet has at least one matching type.
+        for mass in &[0.01, 0.5, 1., 3., 5.185, 50., 300.] {
+            for temperature in &[10., 124.5, 200., 288., 400., 700., 2_000.] {
+                assert!(
+                    planet_gen
+                        .types
+                        .iter()
+                        .any(|kind| kind.matches(*mass, *temperature)),
+                    "No type for mass {} and temperature {}",
+                    mass,
+                    temperature
+                );
+            }
+        }
+    }
+}
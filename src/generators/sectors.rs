@@ -1,10 +1,8 @@
 use rand::{seq, ChaChaRng, SeedableRng};
 use rayon::prelude::*;
 use std::{
-    collections::HashMap,
     sync::atomic::{AtomicBool, Ordering},
     time::Instant,
-    usize::MAX,
 };
 
 use config::GameConfig;
@@ -27,7 +25,7 @@ impl SectorGen {
         let now = Instant::now();
 
         info!("Simulating expansion for initial sectors...");
-        let seed: &[_] = &[config.map_seed as u32];
+        let seed: &[_] = &[config.map_seed];
         let mut rng: ChaChaRng = ChaChaRng::from_seed(seed);
 
         // Setup initial centroids
@@ -38,8 +36,9 @@ impl SectorGen {
                 .cloned()
                 .collect::<Vec<_>>();
 
-        // System to cluster_id mapping
-        let mut cluster_map: HashMap<Point, usize> = system_locations
+        // System to cluster_id mapping, kept in a vector so that iteration order
+        // and thereby the centroids are deterministic.
+        let mut cluster_map: Vec<(Point, usize)> = system_locations
             .into_iter()
             .map(|point| (point, 0))
             .collect();
@@ -106,7 +105,7 @@ impl SectorGen {
                 let mut faction_rng: ChaChaRng = SeedableRng::from_seed(sector_seed);
                 Sector {
                     system_locations,
-                    faction: Faction::random_faction(&mut faction_rng),
+                    faction: Faction::random_faction(&mut faction_rng, &config.factions),
                 }
             })
             .collect::<Vec<Sector>>();
@@ -128,7 +127,7 @@ impl SectorGen {
                 .fold(0, |acc, sec| acc.max(sec.system_locations.len())),
             sectors
                 .iter()
-                .fold(MAX, |acc, sec| acc.min(sec.system_locations.len())),
+                .fold(usize::MAX, |acc, sec| acc.min(sec.system_locations.len())),
             ((now.elapsed().as_secs() * 1_000) + u64::from(now.elapsed().subsec_millis())),
             sectors.iter().fold(0, |acc, sec| acc
                 + match sec.faction {
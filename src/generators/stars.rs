@@ -1,34 +1,177 @@
-use entities::{Star, StarType};
+use config::StarConfig;
+use entities::{LuminosityClass, Star, StarClass};
 use rand;
 use statrs::distribution::{Distribution, Gamma};
 
 /// Basic non deterministic name generator for generating new Stars.
 pub struct StarGen {
     mass_gen: Gamma,
+    config: StarConfig,
 }
 
 impl StarGen {
+    /// Age of the oldest stars, in billions of years.
+    const MAX_AGE: f64 = 13.;
+    /// Smallest mass of stars which become supergiants rather than giants.
+    const SUPERGIANT_MASS: f64 = 8.;
+
     /// Create a new Star generator which loads the star resources needed.
-    pub fn new() -> Self {
-        let mass_gen = Gamma::new(2., 1.5).unwrap();
-        StarGen { mass_gen }
+    pub fn new(config: &StarConfig) -> Self {
+        let mass_gen = Gamma::new(config.mass_shape, config.mass_rate).unwrap();
+        StarGen {
+            mass_gen,
+            config: config.clone(),
+        }
+    }
+
+    /// Generates the stars of a system, the heaviest star first followed by
+    /// its companions ordered by their separation from it.
+    pub fn generate_stars<R: rand::Rng>(&self, gen: &mut R) -> Vec<Star> {
+        let random_val: f64 = gen.gen();
+        let count = if random_val < self.config.trinary_chance {
+            3
+        } else if random_val < self.config.trinary_chance + self.config.binary_chance {
+            2
+        } else {
+            1
+        };
+        let mut stars = (0..count)
+            .map(|_| self.generate(gen).unwrap())
+            .collect::<Vec<_>>();
+        stars.sort_by(|a, b| b.mass.partial_cmp(&a.mass).unwrap());
+
+        // Separations are distributed uniformly on a logarithmic scale.
+        let (min, max) = (
+            self.config.min_separation.ln(),
+            self.config.max_separation.ln(),
+        );
+        let mut separations = (1..count)
+            .map(|_| (min + (max - min) * gen.gen::<f64>()).exp())
+            .collect::<Vec<_>>();
+        separations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (star, separation) in stars.iter_mut().skip(1).zip(separations) {
+            star.separation = separation;
+        }
+        stars
     }
 
     /// Generates a new Star from the _distribution_ using the provided random
     /// generator.
     pub fn generate<R: rand::Rng>(&self, gen: &mut R) -> Option<Star> {
         // Do not want too small stars.
-        let mass = self.mass_gen.sample(gen).max(0.1);
+        let mass = self.mass_gen.sample(gen).max(self.config.min_mass);
 
-        // Stars with high mass are binary stars.
-        let startype = if mass > 3. {
-            StarType::Binary
+        // Stellar remnants are picked by their frequency rather than by
+        // evolving the star, the mass is that of the original star.
+        let random_val: f64 = gen.gen();
+        let remnants = [
+            (StarClass::BlackHole, self.config.black_hole_chance),
+            (StarClass::NeutronStar, self.config.neutron_star_chance),
+            (StarClass::WhiteDwarf, self.config.white_dwarf_chance),
+        ];
+        let mut cumulative = 0.;
+        for (class, chance) in remnants.iter() {
+            cumulative += chance;
+            if random_val < cumulative {
+                return Some(StarGen::remnant(gen, class.clone(), mass));
+            }
+        }
+
+        // Main sequence lifetime in billions of years, only stars with a
+        // lifetime shorter than the age of the oldest stars can be giants.
+        let lifetime = 10. * mass.powf(-2.5);
+        let can_be_giant = lifetime < StarGen::MAX_AGE;
+        let star = if can_be_giant && gen.gen::<f64>() < self.config.giant_chance {
+            // Giants have expanded and cooled at the end of their lifetime.
+            let (class, radius) = if mass >= StarGen::SUPERGIANT_MASS {
+                (LuminosityClass::Supergiant, 30. * 10f64.powf(gen.gen()))
+            } else {
+                (LuminosityClass::Giant, 10. * 10f64.powf(gen.gen()))
+            };
+            let temperature = 3_500. + 1_500. * gen.gen::<f64>();
+            let luminosity = Star::black_body_luminosity(radius, temperature);
+            Star::new(
+                mass,
+                luminosity,
+                radius,
+                lifetime,
+                StarClass::classify(temperature, class),
+            )
         } else {
-            StarType::Single
+            // Mass-luminosity and mass-radius relations, stars near the end of
+            // their lifetime have started to expand.
+            let age = gen.gen::<f64>() * lifetime.min(StarGen::MAX_AGE);
+            let luminosity = mass.powf(3.5);
+            let (class, radius) = if age > 0.9 * lifetime {
+                (LuminosityClass::Subgiant, 1.5 * mass.powf(0.8))
+            } else {
+                (LuminosityClass::MainSequence, mass.powf(0.8))
+            };
+            let temperature = Star::surface_temperature(luminosity, radius);
+            Star::new(
+                mass,
+                luminosity,
+                radius,
+                age,
+                StarClass::classify(temperature, class),
+            )
         };
+        Some(star)
+    }
 
-        // Mass-luminosity relation.
-        let luminosity = mass.powf(3.5);
-        Some(Star::new(mass, luminosity, startype))
+    /// Generates the remnant of a star of the given mass.
+    fn remnant<R: rand::Rng>(gen: &mut R, class: StarClass, initial_mass: f64) -> Star {
+        let age = 1. + (StarGen::MAX_AGE - 1.) * gen.gen::<f64>();
+        let (mass, radius, temperature) = match class {
+            // White dwarfs are about the size of the Earth and cool over time.
+            StarClass::WhiteDwarf => {
+                let mass = (0.5 + 0.1 * initial_mass).min(1.4);
+                (
+                    mass,
+                    0.012 * mass.powf(-1. / 3.),
+                    30_000. * (age / 0.1).powf(-0.35),
+                )
+            }
+            // Neutron stars have a radius of about 10 km.
+            StarClass::NeutronStar => (
+                1.4 + 0.6 * gen.gen::<f64>(),
+                1.4e-5,
+                1_000_000. / age.sqrt(),
+            ),
+            // Black holes only emit light when accreting matter.
+            _ => {
+                let mass = 3. + 2. * initial_mass;
+                (mass, 4.24e-6 * mass, 0.)
+            }
+        };
+        let luminosity = Star::black_body_luminosity(radius, temperature);
+        Star::new(mass, luminosity, radius, age, class)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entities::SpectralClass;
+    use rand::{ChaChaRng, SeedableRng};
+
+    #[test]
+    fn test_classification() {
+        match StarClass::classify(Star::SOLAR_TEMPERATURE, LuminosityClass::MainSequence) {
+            StarClass::Normal {
+                spectral, subclass, ..
+            } => assert_eq!((spectral, subclass), (SpectralClass::G, 2)),
+            class => panic!("Expected a G star, got {}", class),
+        }
+
+        let config = StarConfig {
+            white_dwarf_chance: 1.,
+            ..StarConfig::default()
+        };
+        let mut rng = ChaChaRng::from_seed(&[1]);
+        let star = StarGen::new(&config).generate(&mut rng).unwrap();
+        assert_eq!(star.class, StarClass::WhiteDwarf);
+        assert!(star.radius < 0.02);
+        assert!(star.temperature > 4_000.);
     }
 }
@@ -2,33 +2,44 @@ use rand::{ChaChaRng, Rng, SeedableRng};
 use statrs::distribution::{Distribution, Poisson};
 use std::f64;
 
+use config::{FactionsConfig, GameConfig};
 use entities::Faction;
 use entities::{
-    PlanetBuilder, {Reputation, SystemBuilder, SystemSecurity, SystemState},
+    Orbit, PlanetBuilder, PlanetType, Star,
+    {Reputation, SystemBuilder, SystemSecurity, SystemState},
 };
-use generators::{planets::PlanetGen, stars::StarGen};
+use generators::{bodies::BodyGen, planets::PlanetGen, stars::StarGen};
 use utils::Point;
 
 /// Used for generating systems.
 pub struct SystemGen {
     num_planets_gen: Poisson,
+    min_planets: u32,
+    factions: FactionsConfig,
     star_gen: StarGen,
     planet_gen: PlanetGen,
+    body_gen: BodyGen,
 }
 
 impl SystemGen {
     /// Create a new system generator.
-    pub fn new() -> SystemGen {
+    pub fn new(config: &GameConfig) -> SystemGen {
         // Create Star generator.
-        let star_gen = StarGen::new();
+        let star_gen = StarGen::new(&config.stars);
 
         // Create Planet generator.
-        let planet_gen = PlanetGen::new();
+        let planet_gen = PlanetGen::new(&config.planets);
+
+        // Create generator of moons, belts and stations.
+        let body_gen = BodyGen::new(&config.planets);
 
         SystemGen {
-            num_planets_gen: Poisson::new(3.).unwrap(),
+            num_planets_gen: Poisson::new(config.planets.mean_count).unwrap(),
+            min_planets: config.planets.min_count,
+            factions: config.factions.clone(),
             star_gen,
             planet_gen,
+            body_gen,
         }
     }
 
@@ -43,45 +54,81 @@ impl SystemGen {
         let seed: &[_] = &[hash as u32];
         let mut rng = ChaChaRng::from_seed(seed);
 
-        let star = self.star_gen.generate(&mut rng).unwrap();
+        let stars = self.star_gen.generate_stars(&mut rng);
 
-        // TODO: Replace constant in config.
-        let num_planets =
-            (self.num_planets_gen.sample::<ChaChaRng>(&mut rng).round() as u32).max(1);
+        let num_planets = (self.num_planets_gen.sample::<ChaChaRng>(&mut rng).round() as u32)
+            .max(self.min_planets);
 
         // Fallback to planet name: Unnamed if no name could be generated.
         let satelites: Vec<PlanetBuilder> = (0..num_planets)
             .map(|_| {
-                let mut builder = self.planet_gen.generate(&mut rng).unwrap();
+                let host = rng.gen_range(0, stars.len());
+                let mut builder = self.planet_gen.generate(&mut rng, &stars[host]).unwrap();
                 let mass = builder.mass.unwrap();
+                let (orbit, orbit_distance) =
+                    SystemGen::stable_orbit(&stars, host, builder.orbit_distance.unwrap());
                 let surface_temperature = PlanetGen::calculate_surface_temperature(
-                    builder.orbit_distance.unwrap(),
-                    &star,
+                    orbit_distance,
+                    SystemGen::effective_luminosity(&stars, orbit, orbit_distance),
+                );
+                let kind = self
+                    .planet_gen
+                    .predict_type(&mut rng, surface_temperature, mass);
+                let radius = PlanetGen::calculate_radius(mass, kind);
+                let gravity = PlanetGen::calculate_gravity(mass, radius);
+                let atmosphere = PlanetGen::predict_atmosphere(
+                    &mut rng,
+                    kind,
+                    mass,
+                    radius,
+                    surface_temperature,
+                );
+                let orbital_period = PlanetGen::calculate_orbital_period(
+                    orbit_distance,
+                    SystemGen::central_mass(&stars, orbit, orbit_distance),
+                );
+                let habitability =
+                    PlanetGen::calculate_habitability(surface_temperature, gravity, &atmosphere);
+                let economic_type = PlanetGen::predict_economy(&mut rng, kind);
+                let moons = self.body_gen.generate_moons(
+                    &mut rng,
+                    &self.planet_gen,
+                    kind,
+                    mass,
+                    surface_temperature,
                 );
-                let planet_type = PlanetGen::predict_type(&mut rng, surface_temperature, mass);
-                let economic_type = PlanetGen::predict_economy(&mut rng, &planet_type);
                 builder
+                    .radius(radius)
+                    .gravity(gravity)
+                    .atmosphere(atmosphere)
+                    .orbit(orbit)
+                    .orbit_distance(orbit_distance)
+                    .orbital_period(orbital_period)
                     .surface_temperature(surface_temperature)
-                    .planet_type(planet_type)
-                    .economic_type(economic_type);
+                    .habitability(habitability)
+                    .planet_type(PlanetType(kind.name.clone()))
+                    .economic_type(economic_type)
+                    .moons(moons);
                 builder
             })
             .collect();
 
         // Set the security level based on faction and a probability.
         let random_val: f64 = rng.gen();
-        let security_level = match faction {
-            Faction::Empire if random_val < 0.5 => SystemSecurity::High,
-            Faction::Empire if random_val >= 0.5 => SystemSecurity::Medium,
-            Faction::Federation if random_val < 0.4 => SystemSecurity::Low,
-            Faction::Federation if random_val < 0.8 => SystemSecurity::Medium,
-            Faction::Federation if random_val >= 0.8 => SystemSecurity::High,
-            Faction::Cartel if random_val < 0.5 => SystemSecurity::Medium,
-            Faction::Cartel if random_val >= 0.5 => SystemSecurity::Anarchy,
-            Faction::Independent if random_val < 0.5 => SystemSecurity::Anarchy,
-            Faction::Independent if random_val >= 0.5 => SystemSecurity::Low,
-            _ => SystemSecurity::Low,
-        };
+        let security_level = self.random_security(&faction, random_val);
+
+        // Belts orbit like planets, stations are placed once all other bodies
+        // are known.
+        let belts = self
+            .body_gen
+            .generate_belts(&mut rng, &stars)
+            .into_iter()
+            .map(|(host, distance)| {
+                let (orbit, orbit_distance) = SystemGen::stable_orbit(&stars, host, distance);
+                BodyGen::belt(&mut rng, orbit, orbit_distance)
+            })
+            .collect::<Vec<_>>();
+        let stations = BodyGen::generate_stations(&mut rng, &satelites, &belts);
 
         let mut system = SystemBuilder::default();
         system
@@ -90,7 +137,137 @@ impl SystemGen {
             .security(security_level)
             .state(SystemState::Boom)
             .reputation(Reputation::default())
-            .star(star);
+            .stars(stars)
+            .belts(belts)
+            .stations(stations);
         (system, satelites)
     }
+
+    /// Returns the orbit of a planet at the given distance from the host star,
+    /// which is only stable well within the distance to the nearest other
+    /// star. Other planets orbit the inner pair of stars, well outside their
+    /// separation, while wider companions may stay outside the orbit.
+    fn stable_orbit(stars: &[Star], host: usize, distance: f64) -> (Orbit, f64) {
+        let nearest = stars
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != host)
+            .map(|(_, star)| (star.separation - stars[host].separation).abs())
+            .fold(f64::INFINITY, f64::min);
+        if distance < nearest / 3. {
+            return (Orbit::Star(host), distance);
+        }
+
+        // Companions are ordered by separation, the first one forms the inner
+        // pair with the primary.
+        let inner = stars[1].separation;
+        if distance > 2. * inner {
+            (Orbit::Circumbinary, distance)
+        } else {
+            (Orbit::Circumbinary, distance + 2. * inner)
+        }
+    }
+
+    /// Returns the luminosity of a single star at the orbit distance which
+    /// would heat a planet as much as all the stars of the system combined.
+    fn effective_luminosity(stars: &[Star], orbit: Orbit, distance: f64) -> f64 {
+        stars
+            .iter()
+            .map(|star| {
+                let star_distance = match orbit {
+                    Orbit::Star(host) => (star.separation - stars[host].separation)
+                        .abs()
+                        .max(distance),
+                    Orbit::Circumbinary => star.separation.max(distance),
+                };
+                star.luminosity * (distance / star_distance).powi(2)
+            })
+            .sum()
+    }
+
+    /// Returns the mass orbited by a planet with the given orbit, in solar
+    /// masses. Circumbinary planets orbit the stars within their orbit.
+    fn central_mass(stars: &[Star], orbit: Orbit, distance: f64) -> f64 {
+        match orbit {
+            Orbit::Star(host) => stars[host].mass,
+            Orbit::Circumbinary => stars
+                .iter()
+                .filter(|star| star.separation < distance)
+                .map(|star| star.mass)
+                .sum(),
+        }
+    }
+
+    /// Picks the first security level of the faction whose cumulative chance
+    /// exceeds the random value, falling back to the last level.
+    fn random_security(&self, faction: &Faction, random_val: f64) -> SystemSecurity {
+        let levels = &self.factions.get(faction).security;
+        let mut cumulative = 0.;
+        levels
+            .iter()
+            .find(|security| {
+                cumulative += security.chance;
+                random_val < cumulative
+            })
+            .or_else(|| levels.last())
+            .map_or(SystemSecurity::Low, |security| security.level.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entities::{Atmosphere, AtmosphereType, StarClass};
+
+    #[test]
+    fn test_stable_orbit() {
+        let mut companion = Star::new(1., 1., 1., 4.6, StarClass::WhiteDwarf);
+        companion.separation = 3_000.;
+        let stars = vec![Star::new(1., 1., 1., 4.6, StarClass::WhiteDwarf), companion];
+
+        assert_eq!(
+            SystemGen::stable_orbit(&stars, 1, 500.),
+            (Orbit::Star(1), 500.)
+        );
+        assert_eq!(
+            SystemGen::stable_orbit(&stars, 0, 1_500.),
+            (Orbit::Circumbinary, 7_500.)
+        );
+
+        // Far away planets get the light of both stars, close ones mostly
+        // that of their host.
+        let circumbinary = SystemGen::effective_luminosity(&stars, Orbit::Circumbinary, 7_500.);
+        assert!((circumbinary - 2.).abs() < 1e-9);
+        let s_type = SystemGen::effective_luminosity(&stars, Orbit::Star(1), 500.);
+        assert!(s_type > 1. && s_type < 1.1);
+    }
+
+    #[test]
+    fn test_habitable_circumbinary() {
+        // A close pair of sun like stars with a wide companion, which used to
+        // push circumbinary planets beyond the companion where they froze.
+        let mut companion = Star::new(1., 1., 1., 4.6, StarClass::WhiteDwarf);
+        companion.separation = 20.;
+        let mut wide = Star::new(0.5, 0.1, 0.5, 4.6, StarClass::WhiteDwarf);
+        wide.separation = 40_000.;
+        let stars = vec![
+            Star::new(1., 1., 1., 4.6, StarClass::WhiteDwarf),
+            companion,
+            wide,
+        ];
+
+        let (orbit, distance) = SystemGen::stable_orbit(&stars, 0, 700.);
+        assert_eq!((orbit, distance), (Orbit::Circumbinary, 700.));
+        assert_eq!(SystemGen::central_mass(&stars, orbit, distance), 2.);
+        let surface_temperature = PlanetGen::calculate_surface_temperature(
+            distance,
+            SystemGen::effective_luminosity(&stars, orbit, distance),
+        );
+        let atmosphere = Atmosphere {
+            kind: AtmosphereType::Breathable,
+            pressure: 1.,
+        };
+        let habitability = PlanetGen::calculate_habitability(surface_temperature, 1., &atmosphere);
+        assert!(habitability > 0.5, "{}", habitability);
+    }
 }
@@ -13,27 +13,42 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate anyhow;
+extern crate bincode;
 extern crate clap;
 extern crate serde_json;
 extern crate spade;
 extern crate statrs;
 extern crate toml;
 
+mod clock;
 mod config;
+mod determinism;
 mod economy;
 mod entities;
+mod export;
 mod game;
 mod generators;
+mod navigation;
+mod player;
 mod resources;
+mod server;
 mod simulator;
+mod tui;
 mod utils;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use clap::{AppSettings, Clap};
 use log::LevelFilter;
-use std::{fs::File, io, io::Read};
+use std::{
+    fs::File,
+    io,
+    io::{BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
-fn main() {
+fn main() -> Result<()> {
     // Init logger
     setup_logger().unwrap();
 
@@ -42,30 +57,203 @@ fn main() {
 
     match opts.subcmd {
         SubCommand::NewGame(t) => {
-            // Display title.
-            let title = include_str!("../res/title.txt");
-            println!("{}", title);
-
-            // Gets a value for config if supplied by user, or defaults to "genconfig.toml"
-            let config = match parse_config(&t.config_path) {
-                Ok(config) => config,
-                Err(e) => {
-                    warn!(
-                        "Failed to get specified config at {}: due to {}. Using default",
-                        &t.config_path, e
+            display_title();
+
+            // Start simulator
+            let mut simulator = simulator::Simulator::new(load_config(&t.config_path)?);
+            simulator.new_game()?;
+
+            let save_path = simulator::new_save_path(Path::new(&t.data_dir));
+            simulator.save_game(&save_path)?;
+            println!("Game saved to {}", save_path.display());
+        }
+        SubCommand::LoadGame(t) => {
+            display_title();
+
+            let save_path = Path::new(&t.path);
+            let mut simulator = simulator::Simulator::new(load_config(&t.config_path)?);
+            simulator.load_game(save_path)?;
+            simulator.save_game(save_path)?;
+            println!("Game saved to {}", save_path.display());
+        }
+        SubCommand::Continue(t) => {
+            display_title();
+
+            let save_path = simulator::latest_save(Path::new(&t.data_dir))?;
+            let mut simulator = simulator::Simulator::new(load_config(&t.config_path)?);
+            simulator.load_game(&save_path)?;
+            simulator.save_game(&save_path)?;
+            println!("Game saved to {}", save_path.display());
+        }
+        SubCommand::Serve(t) => {
+            let (_, game, save_path) = open_or_new_game(&t.save)?;
+            if let Some(clock) = t.clock {
+                game.set_clock(clock);
+            }
+            let server = server::Server::new(game, &save_path);
+            server.serve(t.port, Duration::from_secs(t.tick))?;
+        }
+        SubCommand::Play(t) => {
+            let (_, game, save_path) = open_or_new_game(&t)?;
+            let mut tui = tui::Tui::new(tui::AnsiBackend::new()?, game, save_path);
+            tui.run()?;
+        }
+        SubCommand::Advance(t) => {
+            // Load without catching up to the clock, so exactly the given
+            // number of days pass.
+            let save_path = match t.save.save_path {
+                Some(ref path) => Path::new(path).to_path_buf(),
+                None => simulator::latest_save(Path::new(&t.save.data_dir))?,
+            };
+            let game = game::Game::load(&save_path)?;
+            game.advance(t.days);
+            game.save(&save_path)?;
+            println!(
+                "Advanced {} days to {}",
+                t.days,
+                game.updated().format("%Y-%m-%d")
+            );
+        }
+        SubCommand::InitConfig(t) => {
+            if Path::new(&t.output).exists() && !t.force {
+                bail!("{} already exists, use --force to overwrite it", t.output);
+            }
+            let config = config::GameConfig::preset(t.preset);
+            let mut file = File::create(&t.output)?;
+            file.write_all(config.to_documented_toml().as_bytes())?;
+            println!("Config written to {}", t.output);
+        }
+        SubCommand::CheckConfig(t) => {
+            let config = parse_config(&t.config_path)?;
+            println!(
+                "Config {} is valid: {} systems in {} sectors, map seed {}",
+                t.config_path, config.number_of_systems, config.number_of_sectors, config.map_seed
+            );
+        }
+        SubCommand::VerifyDeterminism(t) => {
+            let config = load_config(&t.config_path)?;
+
+            // Generate once more with the first thread count, so that repeated
+            // runs are compared as well as different thread counts.
+            let reference = determinism::Fingerprint::generate(&config, t.threads[0], t.ticks)?;
+            for threads in &t.threads {
+                let fingerprint = determinism::Fingerprint::generate(&config, *threads, t.ticks)?;
+                println!("{} threads: {:016x}", threads, fingerprint.hash());
+                if let Some(entity) = reference.first_divergence(&fingerprint) {
+                    bail!(
+                        "Generation with {} threads diverged from the reference, first at {}",
+                        threads,
+                        entity
                     );
-                    config::GameConfig::default()
                 }
+            }
+            println!(
+                "Galaxy and economy after {} ticks are identical across all runs",
+                t.ticks
+            );
+        }
+        SubCommand::ExportMap(t) => {
+            let galaxy = generators::generate_galaxy(&load_config(&t.config_path)?)?;
+            let mut file = BufWriter::new(File::create(&t.output)?);
+            match t.format {
+                export::MapFormat::Svg => export::render_svg(&galaxy, t.labels, &mut file)?,
+            }
+            file.flush()?;
+            println!("Galaxy map written to {}", t.output);
+        }
+        SubCommand::Export(t) => {
+            // Export either a freshly generated game or a saved one.
+            let game = if t.generate {
+                let mut simulator = simulator::Simulator::new(load_config(&t.save.config_path)?);
+                simulator.new_game()?;
+                simulator.game().unwrap()
+            } else {
+                open_saved_game(&t.save)?.1
             };
+            let galaxy = game.galaxy.lock().unwrap();
+            let economy = game.economy.lock().unwrap();
+            for path in
+                export::export_tables(&galaxy, &economy, t.format, Path::new(&t.output_dir))?
+            {
+                println!("Wrote {}", path.display());
+            }
+        }
+        SubCommand::Route(t) => {
+            let (_, game, _) = open_saved_game(&t.save)?;
+            let galaxy = game.galaxy.lock().unwrap();
+            let destination = galaxy
+                .system_by_name(&t.destination)
+                .ok_or_else(|| anyhow!("No system named {}", t.destination))?;
+            let objective = if t.shortest {
+                navigation::RouteObjective::ShortestDistance
+            } else {
+                navigation::RouteObjective::FewestJumps
+            };
+            let player = game.player.lock().unwrap();
+            let route = galaxy.route(
+                &player.location(),
+                &destination.location,
+                player.ship().jump_range,
+                objective,
+            )?;
 
-            // Start simulator
-            let mut simulator = simulator::Simulator::new(config);
-
-            simulator.new_game();
+            println!(
+                "Route to {}: {} jumps, {:.2} ly",
+                destination.name,
+                route.jumps(),
+                route.distance
+            );
+            for location in &route.systems {
+                println!("  {}", galaxy.system(location).unwrap().name);
+            }
+        }
+        SubCommand::Jump(t) => {
+            let (simulator, game, save_path) = open_saved_game(&t.save)?;
+            let destination = game
+                .galaxy
+                .lock()
+                .unwrap()
+                .system_by_name(&t.destination)
+                .map(|system| system.location)
+                .ok_or_else(|| anyhow!("No system named {}", t.destination))?;
+            game.jump(&destination)?;
+            println!(
+                "Jumped to {}, {:.2} fuel left",
+                t.destination,
+                game.player.lock().unwrap().ship().fuel()
+            );
+            simulator.save_game(&save_path)?;
+        }
+        SubCommand::Refuel(t) => {
+            let (simulator, game, save_path) = open_saved_game(&t)?;
+            let amount = game.player.lock().unwrap().refuel();
+            println!("Bought {:.2} fuel", amount);
+            simulator.save_game(&save_path)?;
         }
+        SubCommand::BestTrades(t) => {
+            let (_, game, _) = open_saved_game(&t.save)?;
+            let runs = game.best_trades(t.max_jumps);
+            let galaxy = game.galaxy.lock().unwrap();
+            for run in runs.iter().take(t.count) {
+                println!(
+                    "{} to {} ({} jumps): {} units, buy {} sell {}, profit {} ({:.0} per jump)",
+                    run.commodity,
+                    galaxy.system(&run.destination).unwrap().name,
+                    run.jumps,
+                    run.amount,
+                    run.buy_price,
+                    run.sell_price,
+                    run.profit,
+                    run.profit_per_jump
+                );
+            }
+        }
+        SubCommand::Buy(t) => trade(t, true)?,
+        SubCommand::Sell(t) => trade(t, false)?,
     }
 
     // TODO: Implement the rest of the program.
+    Ok(())
 }
 
 /// This doc string acts as a help message when the user runs '--help'
@@ -85,7 +273,40 @@ struct Opts {
 enum SubCommand {
     #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
     NewGame(NewGame),
-    //TODO: Add additional subcommands; serve (for server) etc.
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    LoadGame(LoadGame),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Continue(Continue),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Serve(Serve),
+    /// Play the game in an interactive terminal interface
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Play(SaveOptions),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Advance(Advance),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    InitConfig(InitConfig),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    CheckConfig(CheckConfig),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    VerifyDeterminism(VerifyDeterminism),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    ExportMap(ExportMap),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Export(Export),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Route(Route),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Jump(Jump),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    BestTrades(BestTrades),
+    /// Fill up the fuel tank of the player's ship
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Refuel(SaveOptions),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Buy(TradeOrder),
+    #[clap(version = "1.3", author = "Viktor H. <viktor.holmgren@gmail.com>")]
+    Sell(TradeOrder),
 }
 
 /// Subcommand for generating a new world.
@@ -93,15 +314,276 @@ enum SubCommand {
 struct NewGame {
     #[clap(short, long, default_value = "genconfig.toml")]
     config_path: String,
+    /// Directory in which the new game is saved
+    #[clap(short, long, default_value = "saves")]
+    data_dir: String,
+}
+
+/// Subcommand for loading an existing game from a save file.
+#[derive(Clap)]
+struct LoadGame {
+    /// Path to the save file to load
+    path: String,
+    #[clap(short, long, default_value = "genconfig.toml")]
+    config_path: String,
+}
+
+/// Subcommand for continuing the most recently saved game.
+#[derive(Clap)]
+struct Continue {
+    #[clap(short, long, default_value = "genconfig.toml")]
+    config_path: String,
+    /// Directory in which to look for save files
+    #[clap(short, long, default_value = "saves")]
+    data_dir: String,
+}
+
+/// Subcommand for running a headless game server.
+#[derive(Clap)]
+struct Serve {
+    /// Local port to listen for clients on
+    #[clap(short, long, default_value = "4242")]
+    port: u16,
+    /// Seconds between each game update
+    #[clap(short, long, default_value = "60")]
+    tick: u64,
+    /// Clock deciding how far the game advances every tick, real-time, manual
+    /// or fixed:<days>. Defaults to the clock of the saved game
+    #[clap(long)]
+    clock: Option<clock::Clock>,
+    #[clap(flatten)]
+    save: SaveOptions,
+}
+
+/// Subcommand for advancing a saved game an exact number of days.
+#[derive(Clap)]
+struct Advance {
+    /// Number of days to advance
+    #[clap(long)]
+    days: u32,
+    #[clap(flatten)]
+    save: SaveOptions,
+}
+
+/// Subcommand for writing the default config with comments explaining each
+/// field.
+#[derive(Clap)]
+struct InitConfig {
+    /// Path of the config to write
+    #[clap(short, long, default_value = "genconfig.toml")]
+    output: String,
+    /// Size of the galaxy, small, medium or huge
+    #[clap(short, long, default_value = "medium")]
+    preset: config::Preset,
+    /// Overwrite the config if it already exists
+    #[clap(short, long)]
+    force: bool,
+}
+
+/// Subcommand for checking a config, reporting every invalid field.
+#[derive(Clap)]
+struct CheckConfig {
+    #[clap(short, long, default_value = "genconfig.toml")]
+    config_path: String,
+}
+
+/// Subcommand for checking that the same config always generates the same
+/// galaxy and economy, regardless of the number of threads used.
+#[derive(Clap)]
+struct VerifyDeterminism {
+    /// Thread counts to generate with, comma separated
+    #[clap(
+        short,
+        long,
+        default_value = "1,4",
+        use_delimiter = true,
+        min_values = 1
+    )]
+    threads: Vec<usize>,
+    /// Number of economy ticks to run before comparing
+    #[clap(long, default_value = "30")]
+    ticks: u32,
+    #[clap(short, long, default_value = "genconfig.toml")]
+    config_path: String,
+}
+
+/// Subcommand for rendering the galaxy generated from a config to an image.
+#[derive(Clap)]
+struct ExportMap {
+    /// Image format, currently only svg is supported
+    #[clap(short, long, default_value = "svg")]
+    format: export::MapFormat,
+    /// Path of the image to write
+    #[clap(short, long, default_value = "galaxy.svg")]
+    output: String,
+    /// Draw the name of each system
+    #[clap(short, long)]
+    labels: bool,
+    #[clap(short, long, default_value = "genconfig.toml")]
+    config_path: String,
+}
+
+/// Subcommand for exporting systems, stars, planets, other bodies, sectors and
+/// market prices as one table each, for analysis in other tools.
+#[derive(Clap)]
+struct Export {
+    /// File format of the tables, jsonl or csv
+    #[clap(short, long, default_value = "jsonl")]
+    format: export::TableFormat,
+    /// Directory in which the tables are written
+    #[clap(short, long, default_value = "export")]
+    output_dir: String,
+    /// Export a newly generated game rather than a saved one
+    #[clap(short, long)]
+    generate: bool,
+    #[clap(flatten)]
+    save: SaveOptions,
+}
+
+/// Options for selecting the saved game to act on.
+#[derive(Clap)]
+struct SaveOptions {
+    /// Save file to use, defaults to the latest save in the data directory
+    #[clap(short, long)]
+    save_path: Option<String>,
+    #[clap(short, long, default_value = "genconfig.toml")]
+    config_path: String,
+    /// Directory in which to look for save files
+    #[clap(short, long, default_value = "saves")]
+    data_dir: String,
+}
+
+/// Subcommand for planning a route from the player's current system.
+#[derive(Clap)]
+struct Route {
+    /// Name of the destination system
+    destination: String,
+    /// Minimize the distance travelled rather than the number of jumps
+    #[clap(long)]
+    shortest: bool,
+    #[clap(flatten)]
+    save: SaveOptions,
+}
+
+/// Subcommand for jumping the player to a system within jump range.
+#[derive(Clap)]
+struct Jump {
+    /// Name of the destination system
+    destination: String,
+    #[clap(flatten)]
+    save: SaveOptions,
 }
 
-/// Try parse the Generation Config at the specified path.
+/// Subcommand for listing the most profitable trades from the player's
+/// current system.
+#[derive(Clap)]
+struct BestTrades {
+    /// Maximum number of jumps to the destination
+    #[clap(short, long, default_value = "5")]
+    max_jumps: usize,
+    /// Number of trades to list
+    #[clap(short = 'n', long, default_value = "10")]
+    count: usize,
+    #[clap(flatten)]
+    save: SaveOptions,
+}
+
+/// Subcommand for buying or selling a commodity at the player's current
+/// system in a saved game.
+#[derive(Clap)]
+struct TradeOrder {
+    /// Commodity to trade, e.g "food" or "consumer items"
+    commodity: economy::Commodity,
+    /// Number of units to trade
+    amount: u64,
+    #[clap(flatten)]
+    save: SaveOptions,
+}
+
+/// Loads the saved game selected by the given options.
+fn open_saved_game(
+    options: &SaveOptions,
+) -> Result<(simulator::Simulator, Arc<game::Game>, PathBuf)> {
+    let save_path = match options.save_path {
+        Some(ref path) => Path::new(path).to_path_buf(),
+        None => simulator::latest_save(Path::new(&options.data_dir))?,
+    };
+    let mut simulator = simulator::Simulator::new(load_config(&options.config_path)?);
+    let game = simulator.load_game(&save_path)?;
+    Ok((simulator, game, save_path))
+}
+
+/// Loads the saved game selected by the given options, or else starts and
+/// saves a brand new game if there is no such save.
+fn open_or_new_game(
+    options: &SaveOptions,
+) -> Result<(simulator::Simulator, Arc<game::Game>, PathBuf)> {
+    let data_dir = Path::new(&options.data_dir);
+    let mut simulator = simulator::Simulator::new(load_config(&options.config_path)?);
+
+    let save_path = match options.save_path {
+        Some(ref path) => Path::new(path).to_path_buf(),
+        None => {
+            simulator::latest_save(data_dir).unwrap_or_else(|_| simulator::new_save_path(data_dir))
+        }
+    };
+    if save_path.exists() {
+        simulator.load_game(&save_path)?;
+    } else {
+        simulator.new_game()?;
+    }
+    simulator.save_game(&save_path)?;
+
+    let game = simulator.game().unwrap();
+    Ok((simulator, game, save_path))
+}
+
+/// Performs the given trade order in its saved game, buying or selling.
+fn trade(order: TradeOrder, buying: bool) -> Result<()> {
+    let (simulator, game, save_path) = open_saved_game(&order.save)?;
+
+    let trade = if buying {
+        game.buy(&order.commodity, order.amount)?
+    } else {
+        game.sell(&order.commodity, order.amount)?
+    };
+    println!(
+        "{} {} {} for {} credits ({} per unit), {} credits left",
+        if buying { "Bought" } else { "Sold" },
+        trade.amount,
+        trade.commodity,
+        trade.total,
+        trade.unit_price,
+        game.player.lock().unwrap().credits()
+    );
+
+    simulator.save_game(&save_path)
+}
+
+/// Display the game title.
+fn display_title() {
+    let title = include_str!("../res/title.txt");
+    println!("{}", title);
+}
+
+/// Gets the config at the given path, or the default config if there is no
+/// such file. Fails if the config is invalid.
+fn load_config(path: &str) -> Result<config::GameConfig> {
+    if !Path::new(path).exists() {
+        warn!("No config found at {}, using default", path);
+        return Ok(config::GameConfig::default());
+    }
+    parse_config(path)
+}
+
+/// Try parse and validate the Generation Config at the specified path.
 fn parse_config(path: &str) -> Result<config::GameConfig> {
-    let mut file = File::open(&path)?;
+    let mut file = File::open(path).with_context(|| format!("Failed to open config {}", path))?;
 
     let mut file_content = String::new();
     file.read_to_string(&mut file_content)?;
-    let config: config::GameConfig = toml::from_str(&file_content)?;
+    let config = config::GameConfig::from_toml(&file_content)
+        .with_context(|| format!("Failed to load config {}", path))?;
     Ok(config)
 }
 
@@ -122,3 +604,45 @@ pub fn setup_logger() -> Result<()> {
         .apply()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ErrorKind;
+
+    #[test]
+    fn test_parse_subcommands() {
+        let opts = Opts::try_parse_from(["gemini", "advance", "--days", "3"]).unwrap();
+        match opts.subcmd {
+            SubCommand::Advance(advance) => assert_eq!(advance.days, 3),
+            _ => panic!("Expected the advance subcommand"),
+        }
+
+        // Clashing options only panic once the subcommand is parsed.
+        let subcommands = [
+            "new-game",
+            "load-game",
+            "continue",
+            "serve",
+            "play",
+            "advance",
+            "init-config",
+            "check-config",
+            "verify-determinism",
+            "export-map",
+            "export",
+            "route",
+            "jump",
+            "best-trades",
+            "refuel",
+            "buy",
+            "sell",
+        ];
+        for subcommand in &subcommands {
+            let error = Opts::try_parse_from(["gemini", subcommand, "--help"])
+                .err()
+                .unwrap();
+            assert_eq!(error.kind, ErrorKind::DisplayHelp, "{}", subcommand);
+        }
+    }
+}
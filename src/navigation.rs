@@ -0,0 +1,244 @@
+use std::{
+    collections::{hash_map::Entry, BinaryHeap, HashMap, VecDeque},
+    error::Error,
+    fmt,
+};
+
+use entities::Galaxy;
+use utils::{OrdPoint, Point};
+
+/// What a planned route should minimize.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum RouteObjective {
+    #[default]
+    FewestJumps,
+    ShortestDistance,
+}
+
+/// A route between two systems, given as the systems jumped through.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Route {
+    pub systems: Vec<Point>,
+    pub distance: f64,
+}
+
+impl Route {
+    /// Returns the number of jumps needed to travel the route.
+    pub fn jumps(&self) -> usize {
+        self.systems.len().saturating_sub(1)
+    }
+}
+
+/// Reasons for which navigation can fail.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NavigationError {
+    UnknownSystem(Point),
+    Unreachable,
+    OutOfRange { distance: f64, jump_range: f64 },
+    InsufficientFuel { required: f64, available: f64 },
+}
+
+impl fmt::Display for NavigationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NavigationError::UnknownSystem(location) => {
+                write!(f, "No system at ({}, {})", location.x, location.y)
+            }
+            NavigationError::Unreachable => {
+                write!(f, "Destination is unreachable with the current jump range")
+            }
+            NavigationError::OutOfRange {
+                distance,
+                jump_range,
+            } => write!(
+                f,
+                "Destination is {:.2} ly away, jump range is only {:.2} ly",
+                distance, jump_range
+            ),
+            NavigationError::InsufficientFuel {
+                required,
+                available,
+            } => write!(
+                f,
+                "Insufficient fuel, {:.2} required but only {:.2} available",
+                required, available
+            ),
+        }
+    }
+}
+
+impl Error for NavigationError {}
+
+impl Galaxy {
+    /// Returns the locations of all systems reachable in one jump from the
+    /// given location, excluding the location itself.
+    pub fn neighbors(&self, location: &Point, jump_range: f64) -> Vec<Point> {
+        self.map
+            .lookup_in_circle(location, &(jump_range * jump_range))
+            .into_iter()
+            .filter(|neighbor| *neighbor != location)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the locations of all systems reachable from the origin in at most
+    /// max_jumps jumps, along with the fewest number of jumps needed.
+    pub fn reachable(
+        &self,
+        origin: &Point,
+        jump_range: f64,
+        max_jumps: usize,
+    ) -> HashMap<Point, usize> {
+        let mut jumps = HashMap::new();
+        let mut queue = VecDeque::new();
+        jumps.insert(*origin, 0);
+        queue.push_back(*origin);
+
+        // Breadth first search, expanding one jump at a time.
+        while let Some(current) = queue.pop_front() {
+            let current_jumps = jumps[&current];
+            if current_jumps == max_jumps {
+                continue;
+            }
+            for neighbor in self.neighbors(&current, jump_range) {
+                if let Entry::Vacant(entry) = jumps.entry(neighbor) {
+                    entry.insert(current_jumps + 1);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        jumps
+    }
+
+    /// Plans a route between the systems at start and destination using A*,
+    /// where no single jump may be longer than jump_range.
+    pub fn route(
+        &self,
+        start: &Point,
+        destination: &Point,
+        jump_range: f64,
+        objective: RouteObjective,
+    ) -> Result<Route, NavigationError> {
+        for location in &[start, destination] {
+            if self.system(location).is_none() {
+                return Err(NavigationError::UnknownSystem(**location));
+            }
+        }
+
+        // Lower bound of the remaining cost, admissible for both objectives.
+        let heuristic = |location: &Point| match objective {
+            RouteObjective::FewestJumps => (location.distance(destination) / jump_range).ceil(),
+            RouteObjective::ShortestDistance => location.distance(destination),
+        };
+
+        let mut frontier = BinaryHeap::new();
+        let mut costs: HashMap<Point, f64> = HashMap::new();
+        let mut came_from: HashMap<Point, Point> = HashMap::new();
+
+        costs.insert(*start, 0.);
+        frontier.push(OrdPoint {
+            point: *start,
+            weight: heuristic(start),
+        });
+
+        while let Some(OrdPoint { point: current, .. }) = frontier.pop() {
+            if current == *destination {
+                return Ok(self.reconstruct_route(&came_from, destination));
+            }
+
+            let current_cost = costs[&current];
+            for neighbor in self.neighbors(&current, jump_range) {
+                let cost = current_cost
+                    + match objective {
+                        RouteObjective::FewestJumps => 1.,
+                        RouteObjective::ShortestDistance => current.distance(&neighbor),
+                    };
+                if costs.get(&neighbor).is_none_or(|&previous| cost < previous) {
+                    costs.insert(neighbor, cost);
+                    came_from.insert(neighbor, current);
+                    frontier.push(OrdPoint {
+                        point: neighbor,
+                        weight: cost + heuristic(&neighbor),
+                    });
+                }
+            }
+        }
+
+        Err(NavigationError::Unreachable)
+    }
+
+    /// Follows the search tree back from the destination to build the route.
+    fn reconstruct_route(&self, came_from: &HashMap<Point, Point>, destination: &Point) -> Route {
+        let mut systems = vec![*destination];
+        while let Some(previous) = came_from.get(systems.last().unwrap()) {
+            systems.push(*previous);
+        }
+        systems.reverse();
+
+        let distance = systems
+            .windows(2)
+            .map(|jump| jump[0].distance(&jump[1]))
+            .sum();
+        Route { systems, distance }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entities::System;
+
+    #[test]
+    fn test_route() {
+        // A line of systems with a shortcut, and a far away island.
+        let galaxy = Galaxy::new(
+            vec![],
+            vec![
+                System::empty_at(Point::new(0., 0.)),
+                System::empty_at(Point::new(3., 0.)),
+                System::empty_at(Point::new(6., 0.)),
+                System::empty_at(Point::new(9., 0.)),
+                System::empty_at(Point::new(4.5, 4.)),
+                System::empty_at(Point::new(100., 100.)),
+            ],
+        );
+        let start = Point::new(0., 0.);
+        let end = Point::new(9., 0.);
+
+        let route = galaxy
+            .route(&start, &end, 3.5, RouteObjective::ShortestDistance)
+            .unwrap();
+        assert_eq!(route.jumps(), 3);
+        assert_eq!(route.distance, 9.);
+
+        let route = galaxy
+            .route(&start, &end, 6.1, RouteObjective::FewestJumps)
+            .unwrap();
+        assert_eq!(route.jumps(), 2);
+        assert_eq!(route.systems.first(), Some(&start));
+        assert_eq!(route.systems.last(), Some(&end));
+
+        assert_eq!(
+            galaxy.route(
+                &start,
+                &Point::new(100., 100.),
+                10.,
+                RouteObjective::FewestJumps
+            ),
+            Err(NavigationError::Unreachable)
+        );
+        assert_eq!(
+            galaxy.route(
+                &start,
+                &Point::new(1., 1.),
+                10.,
+                RouteObjective::FewestJumps
+            ),
+            Err(NavigationError::UnknownSystem(Point::new(1., 1.)))
+        );
+
+        let reachable = galaxy.reachable(&start, 3.5, 2);
+        assert_eq!(reachable.len(), 3);
+        assert_eq!(reachable[&Point::new(6., 0.)], 2);
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use economy::Commodity;
+use navigation::NavigationError;
+use utils::Point;
+
+/// Represents the player, trading and travelling between systems in a ship.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Player {
+    credits: u64,
+    location: Point,
+    ship: Ship,
+}
+
+impl Player {
+    const STARTING_CREDITS: u64 = 50_000;
+    const FUEL_PRICE: u64 = 100;
+
+    /// Create a new player with the starting ship at the given system location.
+    pub fn new(location: Point) -> Self {
+        Player {
+            credits: Player::STARTING_CREDITS,
+            location,
+            ship: Ship::default(),
+        }
+    }
+
+    /// Returns the amount of credits held by the player.
+    pub fn credits(&self) -> u64 {
+        self.credits
+    }
+
+    /// Adds delta amount of credits, saturating at zero.
+    pub fn update_credits(&mut self, delta: i64) {
+        self.credits = (self.credits as i64 + delta).max(0) as u64;
+    }
+
+    /// Returns the location of the system the player is currently in.
+    pub fn location(&self) -> Point {
+        self.location
+    }
+
+    /// Jumps directly to the given location, consuming one unit of fuel per
+    /// light year travelled.
+    pub fn jump(&mut self, destination: &Point) -> Result<(), NavigationError> {
+        let distance = self.location.distance(destination);
+        if distance > self.ship.jump_range {
+            return Err(NavigationError::OutOfRange {
+                distance,
+                jump_range: self.ship.jump_range,
+            });
+        }
+        if distance > self.ship.fuel() {
+            return Err(NavigationError::InsufficientFuel {
+                required: distance,
+                available: self.ship.fuel(),
+            });
+        }
+
+        self.ship.update_fuel(-distance);
+        self.location = *destination;
+        Ok(())
+    }
+
+    /// Fills up the fuel tank as far as the credits allow, returning the
+    /// amount of fuel bought.
+    pub fn refuel(&mut self) -> f64 {
+        let missing = self.ship.fuel_capacity - self.ship.fuel();
+        let amount = missing.min((self.credits / Player::FUEL_PRICE) as f64);
+        self.credits -= (amount * Player::FUEL_PRICE as f64).ceil() as u64;
+        self.ship.update_fuel(amount);
+        amount
+    }
+
+    /// Returns a reference to the player's ship.
+    pub fn ship(&self) -> &Ship {
+        &self.ship
+    }
+
+    /// Returns a mutable reference to the player's ship.
+    pub fn ship_mut(&mut self) -> &mut Ship {
+        &mut self.ship
+    }
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Player::new(Point::origin())
+    }
+}
+
+/// A ship with a cargo hold and a fuel tank, fuel is consumed per light year
+/// travelled.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Ship {
+    pub name: String,
+    pub cargo_capacity: u64,
+    pub fuel_capacity: f64,
+    pub jump_range: f64,
+    fuel: f64,
+    cargo: HashMap<Commodity, u64>,
+}
+
+impl Ship {
+    /// Create a new ship with a full tank and an empty cargo hold.
+    pub fn new(name: &str, cargo_capacity: u64, fuel_capacity: f64, jump_range: f64) -> Self {
+        Ship {
+            name: String::from(name),
+            cargo_capacity,
+            fuel_capacity,
+            jump_range,
+            fuel: fuel_capacity,
+            cargo: HashMap::new(),
+        }
+    }
+
+    /// Returns the amount of fuel left in the tank.
+    pub fn fuel(&self) -> f64 {
+        self.fuel
+    }
+
+    /// Adds delta amount of fuel, limited by the tank capacity.
+    pub fn update_fuel(&mut self, delta: f64) {
+        self.fuel = (self.fuel + delta).max(0.).min(self.fuel_capacity);
+    }
+
+    /// Returns the amount of the given commodity in the cargo hold.
+    pub fn cargo(&self, commodity: &Commodity) -> u64 {
+        *self.cargo.get(commodity).unwrap_or(&0)
+    }
+
+    /// Returns the total amount of cargo in the hold.
+    pub fn cargo_used(&self) -> u64 {
+        self.cargo.values().sum()
+    }
+
+    /// Returns the amount of free space left in the cargo hold.
+    pub fn cargo_space(&self) -> u64 {
+        self.cargo_capacity.saturating_sub(self.cargo_used())
+    }
+
+    /// Adds delta amount of the given commodity to the cargo hold.
+    pub fn update_cargo(&mut self, commodity: &Commodity, delta: i64) {
+        let amount = self.cargo.entry(commodity.clone()).or_insert(0);
+        *amount = (*amount as i64 + delta).max(0) as u64;
+        if *amount == 0 {
+            self.cargo.remove(commodity);
+        }
+    }
+}
+
+impl Default for Ship {
+    /// The starting ship given to new players.
+    fn default() -> Self {
+        Ship::new("Sidewinder", 20, 30., 10.)
+    }
+}
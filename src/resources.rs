@@ -1,10 +1,10 @@
 use serde::de::Deserialize;
 use serde_json;
-use std::{collections::HashMap, str};
+use std::{collections::HashMap, f64, str};
 
 use economy::Commodity;
 use entities::Faction;
-use entities::PlanetEconomy;
+use entities::{AtmosphereType, PlanetEconomy};
 
 /// Generic Resource trait to be implemented by all resource types which should
 /// be loaded at compile time.
@@ -12,6 +12,12 @@ use entities::PlanetEconomy;
 /// resource).
 pub trait Resource: Deserialize<'static> {
     const KEY: &'static str;
+
+    /// Checks the consistency of the resource once loaded, returning a
+    /// description of the first problem found.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 lazy_static! {
@@ -23,20 +29,34 @@ lazy_static! {
             AstronomicalNamesResource::KEY,
             include_str!("../res/astronomical_names.json"),
         );
+        res.insert(
+            FactionNamesResource::KEY,
+            include_str!("../res/faction_names.json"),
+        );
         res.insert(
             AgentResource::KEY,
             include_str!("../res/economic_agents.json"),
         );
+        res.insert(
+            PlanetTypesResource::KEY,
+            include_str!("../res/planet_types.json"),
+        );
         res
     };
 }
 
 /// Attempts to returns the resource with the given type, will return None
-/// if the type has no resource or if the deserialization fails.
+/// if the type has no resource or if the deserialization or validation fails.
 pub fn fetch_resource<T: Resource>() -> Option<T> {
     let res_str = RESOURCES.get(T::KEY).unwrap();
-    match serde_json::from_str(res_str) {
-        Ok(res) => Some(res),
+    match serde_json::from_str::<T>(res_str) {
+        Ok(res) => match res.validate() {
+            Ok(()) => Some(res),
+            Err(msg) => {
+                error!("Invalid {} resource: {}", T::KEY, msg);
+                None
+            }
+        },
         Err(msg) => {
             error!("{}", msg);
             None
@@ -58,6 +78,18 @@ impl Resource for AstronomicalNamesResource {
     const KEY: &'static str = "astronomical_names";
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+/// Resource with corpora of names, and the corpora each faction's naming style
+/// is trained on. Factions not listed use the astronomical names.
+pub struct FactionNamesResource {
+    pub factions: HashMap<Faction, Vec<String>>,
+    pub corpora: HashMap<String, Vec<String>>,
+}
+
+impl Resource for FactionNamesResource {
+    const KEY: &'static str = "faction_names";
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 /// Resource containing all production/consumptions for factions and planets.
 pub struct AgentResource {
@@ -70,3 +102,166 @@ pub struct AgentResource {
 impl Resource for AgentResource {
     const KEY: &'static str = "economic_agents";
 }
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+/// Resource describing every type of planet, the conditions it forms in and
+/// the economies it supports.
+pub struct PlanetTypesResource {
+    pub types: Vec<PlanetTypeInfo>,
+}
+
+impl Resource for PlanetTypesResource {
+    const KEY: &'static str = "planet_types";
+
+    /// Checks that the chances of every type sum to 1 and that every mass and
+    /// temperature is matched by some type.
+    fn validate(&self) -> Result<(), String> {
+        for kind in &self.types {
+            let atmospheres = kind.atmospheres.iter().map(|atmosphere| atmosphere.chance);
+            let economies = kind.economies.iter().map(|economy| economy.chance);
+            for (chances, sum) in &[
+                ("atmosphere", atmospheres.sum::<f64>()),
+                ("economy", economies.sum::<f64>()),
+            ] {
+                if (sum - 1.).abs() > 1e-9 {
+                    return Err(format!(
+                        "{} chances of {} sum to {}, not 1",
+                        chances, kind.name, sum
+                    ));
+                }
+            }
+        }
+
+        // Whether a type matches only changes at the bounds, so checking the
+        // bounds themselves covers every mass and temperature.
+        let bounds = |min: fn(&PlanetTypeInfo) -> f64, max: fn(&PlanetTypeInfo) -> f64| {
+            let mut bounds = Some(0.)
+                .into_iter()
+                .chain(
+                    self.types
+                        .iter()
+                        .flat_map(|kind| vec![min(kind), max(kind)]),
+                )
+                .filter(|bound| bound.is_finite() && *bound >= 0.)
+                .collect::<Vec<_>>();
+            bounds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            bounds.dedup();
+            bounds
+        };
+        let masses = bounds(|kind| kind.min_mass, |kind| kind.max_mass);
+        let temperatures = bounds(|kind| kind.min_temperature, |kind| kind.max_temperature);
+        for mass in &masses {
+            for temperature in &temperatures {
+                if !self
+                    .types
+                    .iter()
+                    .any(|kind| kind.matches(*mass, *temperature))
+                {
+                    return Err(format!(
+                        "no type matches a mass of {} and a temperature of {}",
+                        mass, temperature
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+/// Properties of a single type of planet. A planet can be of any type whose
+/// mass and temperature ranges it falls within, picked by weight.
+pub struct PlanetTypeInfo {
+    pub name: String,
+    pub weight: f64,
+    /// Smallest mass, in Earth masses.
+    #[serde(default)]
+    pub min_mass: f64,
+    /// Mass, in Earth masses, which planets of the type are lighter than.
+    #[serde(default = "unbounded")]
+    pub max_mass: f64,
+    /// Smallest surface temperature, in Kelvin.
+    #[serde(default)]
+    pub min_temperature: f64,
+    /// Surface temperature, in Kelvin, which planets of the type are colder
+    /// than.
+    #[serde(default = "unbounded")]
+    pub max_temperature: f64,
+    /// Radius in Earth radii of a planet of one Earth mass, which grows with
+    /// the mass raised to the exponent.
+    pub radius_factor: f64,
+    pub radius_exponent: f64,
+    /// Largest radius, in Earth radii.
+    #[serde(default = "unbounded")]
+    pub max_radius: f64,
+    /// Giants hold on to their atmosphere regardless of temperature and have
+    /// more moons.
+    #[serde(default)]
+    pub giant: bool,
+    /// Population of the planet, regardless of its habitability.
+    #[serde(default)]
+    pub population: f64,
+    /// Atmospheres of the type, with chances which must sum to 1.
+    pub atmospheres: Vec<AtmosphereChance>,
+    /// Economies of the type, with chances which must sum to 1.
+    pub economies: Vec<EconomyChance>,
+}
+
+impl PlanetTypeInfo {
+    /// Returns true if planets with the given mass and temperature can be of
+    /// this type.
+    pub fn matches(&self, mass: f64, temperature: f64) -> bool {
+        (self.min_mass..self.max_mass).contains(&mass)
+            && (self.min_temperature..self.max_temperature).contains(&temperature)
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+/// Chance of an atmosphere, with a pressure in atmospheres distributed
+/// uniformly on a logarithmic scale between the bounds.
+pub struct AtmosphereChance {
+    pub kind: AtmosphereType,
+    pub chance: f64,
+    pub min_pressure: f64,
+    pub max_pressure: f64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+/// Chance of an economy.
+pub struct EconomyChance {
+    pub economy: PlanetEconomy,
+    pub chance: f64,
+}
+
+/// Default of bounds which are not limited.
+fn unbounded() -> f64 {
+    f64::INFINITY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_planet_types() {
+        let resource = fetch_resource::<PlanetTypesResource>().unwrap();
+        assert_eq!(resource.validate(), Ok(()));
+
+        let mut invalid = resource.clone();
+        invalid.types[0].economies[0].chance += 0.5;
+        let message = invalid.validate().unwrap_err();
+        assert!(message.contains(&invalid.types[0].name), "{}", message);
+
+        // Nothing would form below 10 Kelvin.
+        let mut invalid = resource.clone();
+        for kind in &mut invalid.types {
+            kind.min_temperature = kind.min_temperature.max(10.);
+        }
+        assert_eq!(
+            invalid.validate(),
+            Err(String::from(
+                "no type matches a mass of 0 and a temperature of 0"
+            ))
+        );
+    }
+}
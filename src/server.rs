@@ -0,0 +1,399 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde_json;
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use economy::{Commodity, Trade, TradeRun};
+use entities::{Faction, Galaxy, System, SystemFilter, SystemSecurity};
+use game::Game;
+use navigation::{Route, RouteObjective};
+use player::Player;
+use utils::Point;
+
+/// Maximum distance between a requested location and a system for the two to
+/// be considered the same, guards against rounding in the JSON encoding.
+const LOCATION_TOLERANCE: f64 = 1e-6;
+
+/// Most days a single request may advance the game, the game is locked while
+/// advancing so longer spans would stall every other client.
+const MAX_ADVANCE_DAYS: u32 = 365;
+
+/// Most systems or trade runs a single request may ask for, the galaxy is
+/// locked while they are collected.
+const MAX_COUNT: usize = 1_000;
+
+/// Most jumps away a single request may search for trades, the number of
+/// systems to check grows quickly with the jumps.
+const MAX_JUMPS: usize = 20;
+
+/// Request sent by a client, encoded as a single line of JSON.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Current in game time.
+    Time,
+    /// Short summary of all systems in the galaxy.
+    Systems,
+    /// Summary of the systems closest to the given location matching the filter.
+    Nearest {
+        location: Point,
+        count: usize,
+        #[serde(default)]
+        filter: SystemFilter,
+    },
+    /// Summary of the systems within the radius of the given location matching
+    /// the filter.
+    Within {
+        location: Point,
+        radius: f64,
+        #[serde(default)]
+        filter: SystemFilter,
+    },
+    /// Full description of the system at the given location.
+    System { location: Point },
+    /// Commodity prices in the system at the given location.
+    Prices { location: Point },
+    /// The player, including ship and cargo.
+    Player,
+    /// Buys some amount of a commodity at the player's current system.
+    Buy { commodity: Commodity, amount: u64 },
+    /// Sells some amount of a commodity at the player's current system.
+    Sell { commodity: Commodity, amount: u64 },
+    /// Most profitable commodity runs from the player's current system.
+    BestTrades { max_jumps: usize, count: usize },
+    /// Plans a route from the player's current system to the destination.
+    Route {
+        destination: Point,
+        #[serde(default)]
+        objective: RouteObjective,
+    },
+    /// Jumps the player directly to the system at the destination.
+    Jump { destination: Point },
+    /// Fills up the fuel tank of the player's ship.
+    Refuel,
+    /// Saves the game to the save file it is served from.
+    Save,
+    /// Advances the game exactly the given number of days, at most a year.
+    Advance { days: u32 },
+}
+
+/// Response sent back to the client, encoded as a single line of JSON.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Time { time: DateTime<Utc> },
+    Systems { systems: Vec<SystemSummary> },
+    System { system: System },
+    Prices { prices: Vec<(Commodity, i64)> },
+    Player { player: Player },
+    Trade { trade: Trade },
+    Route { route: Route },
+    TradeRuns { runs: Vec<TradeRun> },
+    Saved { path: PathBuf },
+    Error { message: String },
+}
+
+/// Short description of a system, used when listing systems.
+#[derive(Serialize, Debug)]
+pub struct SystemSummary {
+    pub name: String,
+    pub location: Point,
+    pub faction: Faction,
+    pub security: SystemSecurity,
+}
+
+impl From<&System> for SystemSummary {
+    fn from(system: &System) -> Self {
+        SystemSummary {
+            name: system.name.clone(),
+            location: system.location,
+            faction: system.faction.clone(),
+            security: system.security.clone(),
+        }
+    }
+}
+
+/// Headless game server, exposing the game to clients over TCP.
+pub struct Server {
+    game: Arc<Game>,
+    save_path: PathBuf,
+}
+
+impl Server {
+    /// Creates a new server for the given game, which is saved to save_path.
+    pub fn new(game: Arc<Game>, save_path: &Path) -> Self {
+        Server {
+            game,
+            save_path: save_path.to_path_buf(),
+        }
+    }
+
+    /// Listens for clients on the given local port, updating the game every
+    /// tick. Never returns unless the port could not be bound.
+    pub fn serve(self, port: u16, tick: Duration) -> Result<()> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        info!(
+            "Serving game on {}, clock is {:?}",
+            listener.local_addr()?,
+            self.game.clock()
+        );
+
+        // Keep the game up to date in the background.
+        let game = self.game.clone();
+        thread::spawn(move || loop {
+            thread::sleep(tick);
+            game.update();
+        });
+
+        let server = Arc::new(self);
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let server = server.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = server.handle_client(stream) {
+                            warn!("Client disconnected: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept client: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers requests from the client, one per line, until it disconnects.
+    fn handle_client(&self, stream: TcpStream) -> Result<()> {
+        debug!("Client connected from {}", stream.peer_addr()?);
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = self.handle_line(&line);
+            writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+        }
+        Ok(())
+    }
+
+    /// Parses and handles a single request line.
+    fn handle_line(&self, line: &str) -> Response {
+        serde_json::from_str::<Request>(line)
+            .map_err(|e| anyhow!("Invalid request: {}", e))
+            .and_then(|request| self.handle_request(request))
+            .unwrap_or_else(|e| Response::Error {
+                message: e.to_string(),
+            })
+    }
+
+    /// Performs the given request against the game.
+    fn handle_request(&self, request: Request) -> Result<Response> {
+        match request {
+            Request::Time => Ok(Response::Time {
+                time: self.game.updated(),
+            }),
+            Request::Systems => {
+                let galaxy = self.game.galaxy.lock().unwrap();
+                Ok(Response::Systems {
+                    systems: galaxy.systems().map(SystemSummary::from).collect(),
+                })
+            }
+            Request::Nearest {
+                location,
+                count,
+                filter,
+            } => {
+                check_count(count)?;
+                let galaxy = self.game.galaxy.lock().unwrap();
+                Ok(Response::Systems {
+                    systems: galaxy
+                        .nearest_systems(&location, count, &filter)
+                        .into_iter()
+                        .map(SystemSummary::from)
+                        .collect(),
+                })
+            }
+            Request::Within {
+                location,
+                radius,
+                filter,
+            } => {
+                if !radius.is_finite() || radius < 0. {
+                    return Err(anyhow!("Invalid radius: {}", radius));
+                }
+                let galaxy = self.game.galaxy.lock().unwrap();
+                Ok(Response::Systems {
+                    systems: galaxy
+                        .systems_within(&location, radius, &filter)
+                        .into_iter()
+                        .map(SystemSummary::from)
+                        .collect(),
+                })
+            }
+            Request::System { location } => {
+                let galaxy = self.game.galaxy.lock().unwrap();
+                Ok(Response::System {
+                    system: find_system(&galaxy, &location)?.clone(),
+                })
+            }
+            Request::Prices { location } => {
+                let galaxy = self.game.galaxy.lock().unwrap();
+                let system = find_system(&galaxy, &location)?;
+                Ok(Response::Prices {
+                    prices: self.game.economy.lock().unwrap().commodity_prices(system),
+                })
+            }
+            Request::Player => Ok(Response::Player {
+                player: self.game.player.lock().unwrap().clone(),
+            }),
+            Request::Buy { commodity, amount } => Ok(Response::Trade {
+                trade: self.game.buy(&commodity, amount)?,
+            }),
+            Request::Sell { commodity, amount } => Ok(Response::Trade {
+                trade: self.game.sell(&commodity, amount)?,
+            }),
+            Request::BestTrades { max_jumps, count } => {
+                if max_jumps > MAX_JUMPS {
+                    return Err(anyhow!("Cannot search more than {} jumps away", MAX_JUMPS));
+                }
+                check_count(count)?;
+                let mut runs = self.game.best_trades(max_jumps);
+                runs.truncate(count);
+                Ok(Response::TradeRuns { runs })
+            }
+            Request::Route {
+                destination,
+                objective,
+            } => {
+                let destination = self.resolve_location(&destination)?;
+                Ok(Response::Route {
+                    route: self.game.route(&destination, objective)?,
+                })
+            }
+            Request::Jump { destination } => {
+                let destination = self.resolve_location(&destination)?;
+                self.game.jump(&destination)?;
+                Ok(Response::Player {
+                    player: self.game.player.lock().unwrap().clone(),
+                })
+            }
+            Request::Refuel => {
+                let mut player = self.game.player.lock().unwrap();
+                player.refuel();
+                Ok(Response::Player {
+                    player: player.clone(),
+                })
+            }
+            Request::Advance { days } => {
+                if days > MAX_ADVANCE_DAYS {
+                    return Err(anyhow!(
+                        "Cannot advance more than {} days at once",
+                        MAX_ADVANCE_DAYS
+                    ));
+                }
+                self.game.advance(days);
+                Ok(Response::Time {
+                    time: self.game.updated(),
+                })
+            }
+            Request::Save => {
+                self.game.save(&self.save_path)?;
+                Ok(Response::Saved {
+                    path: self.save_path.clone(),
+                })
+            }
+        }
+    }
+}
+
+impl Server {
+    /// Returns the exact location of the system at the given location.
+    fn resolve_location(&self, location: &Point) -> Result<Point> {
+        let galaxy = self.game.galaxy.lock().unwrap();
+        Ok(find_system(&galaxy, location)?.location)
+    }
+}
+
+/// Returns the system at the given location, allowing for small rounding errors.
+fn find_system<'a>(galaxy: &'a Galaxy, location: &Point) -> Result<&'a System> {
+    galaxy
+        .map
+        .nearest_neighbor(location)
+        .filter(|closest| closest.distance(location) < LOCATION_TOLERANCE)
+        .and_then(|closest| galaxy.system(closest))
+        .ok_or_else(|| anyhow!("No system at ({}, {})", location.x, location.y))
+}
+
+/// Checks that a request does not ask for more results than allowed.
+fn check_count(count: usize) -> Result<()> {
+    if count > MAX_COUNT {
+        return Err(anyhow!(
+            "Cannot request more than {} results at once",
+            MAX_COUNT
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_line() {
+        let server = Server::new(Game::new(), Path::new("unused.sav"));
+
+        match server.handle_line(r#"{"type": "time"}"#) {
+            Response::Time { time } => assert_eq!(time, server.game.updated()),
+            response => panic!("Unexpected response {:?}", response),
+        }
+        match server.handle_line(r#"{"type": "prices", "location": {"x": 1.0, "y": 2.0}}"#) {
+            Response::Error { message } => assert_eq!(message, "No system at (1, 2)"),
+            response => panic!("Unexpected response {:?}", response),
+        }
+        match server
+            .handle_line(r#"{"type": "within", "location": {"x": 0.0, "y": 0.0}, "radius": -1.0}"#)
+        {
+            Response::Error { message } => assert_eq!(message, "Invalid radius: -1"),
+            response => panic!("Unexpected response {:?}", response),
+        }
+        match server.handle_line(r#"{"type": "advance", "days": 4294967295}"#) {
+            Response::Error { message } => {
+                assert_eq!(message, "Cannot advance more than 365 days at once")
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+        match server.handle_line(
+            r#"{"type": "nearest", "location": {"x": 0.0, "y": 0.0}, "count": 1000000}"#,
+        ) {
+            Response::Error { message } => {
+                assert_eq!(message, "Cannot request more than 1000 results at once")
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+        match server.handle_line(r#"{"type": "best_trades", "max_jumps": 1000, "count": 10}"#) {
+            Response::Error { message } => {
+                assert_eq!(message, "Cannot search more than 20 jumps away")
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+        match server.handle_line(r#"{"type": "best_trades", "max_jumps": 2, "count": 1000000}"#) {
+            Response::Error { message } => {
+                assert_eq!(message, "Cannot request more than 1000 results at once")
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+        match server.handle_line("not json") {
+            Response::Error { .. } => {}
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+}
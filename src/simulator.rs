@@ -1,9 +1,20 @@
-use std::sync::Arc;
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use config::GameConfig;
 use economy::Economy;
 use game::Game;
 use generators::generate_galaxy;
+use player::Player;
+use utils::Point;
+
+/// File extension used for save files.
+const SAVE_EXTENSION: &str = "sav";
 
 pub struct Simulator {
     game_state: Option<Arc<Game>>,
@@ -19,20 +30,127 @@ impl Simulator {
         }
     }
 
-    pub fn new_game(&mut self) -> Arc<Game> {
+    /// Generates a new galaxy and economy, failing if the galaxy cannot be
+    /// generated from the config.
+    pub fn new_game(&mut self) -> Result<Arc<Game>> {
         let game_state = Game::new();
 
         // Generate galaxy
         info!("Generating galaxy...");
-        let galaxy = generate_galaxy(&self.game_config);
+        let galaxy = generate_galaxy(&self.game_config)?;
 
         info!("Setting up economy...");
-        *game_state.economy.lock().unwrap() = Economy::new(&galaxy);
+        *game_state.economy.lock().unwrap() = Economy::new(&galaxy, &self.game_config.economy);
+
+        // Start the player in the system closest to the galactic center.
+        if let Some(start) = galaxy.map.nearest_neighbor(&Point::origin()) {
+            *game_state.player.lock().unwrap() = Player::new(*start);
+        }
 
         *game_state.galaxy.lock().unwrap() = galaxy;
 
         game_state.update();
         self.game_state = Some(game_state.clone());
-        game_state
+        Ok(game_state)
+    }
+
+    /// Loads an existing game from the given save file, bringing it up to date.
+    pub fn load_game(&mut self, path: &Path) -> Result<Arc<Game>> {
+        info!("Loading game from {}...", path.display());
+        let game_state = Game::load(path)?;
+
+        game_state.update();
+        self.game_state = Some(game_state.clone());
+        Ok(game_state)
+    }
+
+    /// Returns the current game, if any.
+    pub fn game(&self) -> Option<Arc<Game>> {
+        self.game_state.clone()
+    }
+
+    /// Saves the current game to the given save file.
+    pub fn save_game(&self, path: &Path) -> Result<()> {
+        let game_state = self
+            .game_state
+            .as_ref()
+            .ok_or_else(|| anyhow!("No game is running"))?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        info!("Saving game to {}...", path.display());
+        game_state.save(path)
+    }
+}
+
+/// Returns a new unique save file path in the given data directory, named
+/// after the current time. A sequence number is added if a save was already
+/// created in the same millisecond.
+pub fn new_save_path(data_dir: &Path) -> PathBuf {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S-%3f");
+    (1..)
+        .map(|sequence| match sequence {
+            1 => data_dir.join(format!("{}.{}", stamp, SAVE_EXTENSION)),
+            _ => data_dir.join(format!("{}-{}.{}", stamp, sequence, SAVE_EXTENSION)),
+        })
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+/// Returns the most recently modified save file in the given data directory.
+/// Saves modified at the same time are ordered by name, so the same save is
+/// picked regardless of the directory order.
+pub fn latest_save(data_dir: &Path) -> Result<PathBuf> {
+    let mut saves = vec![];
+    for entry in fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != SAVE_EXTENSION) {
+            continue;
+        }
+        saves.push((fs::metadata(&path)?.modified()?, path));
+    }
+
+    saves
+        .into_iter()
+        .max()
+        .map(|(_, path)| path)
+        .ok_or_else(|| anyhow!("No save files found in {}", data_dir.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env,
+        fs::File,
+        time::{Duration, SystemTime},
+    };
+
+    #[test]
+    fn test_save_paths() {
+        let dir = env::temp_dir().join(format!("gemini-saves-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(latest_save(&dir).is_err());
+
+        // Saves created at once get distinct names.
+        let first = new_save_path(&dir);
+        File::create(&first).unwrap();
+        let second = new_save_path(&dir);
+        File::create(&second).unwrap();
+        assert_ne!(first, second);
+
+        // Ties are broken by name, otherwise the last modified save wins.
+        let set_modified = |path: &Path, time: SystemTime| {
+            let file = File::options().write(true).open(path).unwrap();
+            file.set_modified(time).unwrap();
+        };
+        let now = SystemTime::now();
+        set_modified(&first, now);
+        set_modified(&second, now);
+        assert_eq!(latest_save(&dir).unwrap(), first.clone().max(second));
+        set_modified(&first, now + Duration::from_secs(1));
+        assert_eq!(latest_save(&dir).unwrap(), first);
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
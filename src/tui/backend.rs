@@ -0,0 +1,138 @@
+use std::{
+    env,
+    io::{self, BufRead, Write},
+};
+
+use super::frame::Frame;
+
+/// Terminal used by the interface for drawing frames and reading commands.
+pub trait Backend {
+    /// Returns the size of the screen as (width, height).
+    fn size(&self) -> (usize, usize);
+
+    /// Draws the given frame, replacing whatever was on screen.
+    fn draw(&mut self, frame: &Frame) -> io::Result<()>;
+
+    /// Reads the next command, returns None when there is no more input.
+    fn read_command(&mut self) -> io::Result<Option<String>>;
+
+    /// Restores the terminal to its state before the interface started.
+    fn restore(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Backend for any ANSI terminal, draws to an alternate screen on stdout and
+/// reads one command per line from stdin.
+pub struct AnsiBackend {
+    width: usize,
+    height: usize,
+}
+
+impl AnsiBackend {
+    const DEFAULT_WIDTH: usize = 100;
+    const DEFAULT_HEIGHT: usize = 30;
+
+    /// Switches the terminal to the alternate screen. The size is taken from the
+    /// COLUMNS and LINES environment variables if set.
+    pub fn new() -> io::Result<Self> {
+        let dimension = |name: &str, default: usize| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        let backend = AnsiBackend {
+            width: dimension("COLUMNS", AnsiBackend::DEFAULT_WIDTH),
+            height: dimension("LINES", AnsiBackend::DEFAULT_HEIGHT),
+        };
+
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[?1049h")?;
+        stdout.flush()?;
+        Ok(backend)
+    }
+}
+
+impl Backend for AnsiBackend {
+    fn size(&self) -> (usize, usize) {
+        // The last line is reserved for the prompt.
+        (self.width, self.height.saturating_sub(1))
+    }
+
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        write!(out, "\x1b[2J")?;
+        for y in 0..frame.height {
+            write!(out, "\x1b[{};1H", y + 1)?;
+            let mut current = None;
+            for cell in frame.row(y) {
+                if cell.color != current {
+                    match cell.color {
+                        Some(color) => write!(out, "\x1b[{}m", color.ansi_code())?,
+                        None => write!(out, "\x1b[0m")?,
+                    }
+                    current = cell.color;
+                }
+                write!(out, "{}", cell.symbol)?;
+            }
+            write!(out, "\x1b[0m")?;
+        }
+
+        // Place the prompt on the last line.
+        write!(out, "\x1b[{};1H\x1b[2K> ", frame.height + 1)?;
+        out.flush()
+    }
+
+    fn read_command(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line.trim().to_string())),
+        }
+    }
+
+    fn restore(&mut self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[0m\x1b[?1049l")?;
+        stdout.flush()
+    }
+}
+
+/// Backend which records drawn frames and plays back scripted commands.
+#[cfg(test)]
+pub struct FakeBackend {
+    pub width: usize,
+    pub height: usize,
+    pub commands: ::std::collections::VecDeque<String>,
+    pub frames: Vec<Frame>,
+}
+
+#[cfg(test)]
+impl FakeBackend {
+    pub fn new(width: usize, height: usize, commands: &[&str]) -> Self {
+        FakeBackend {
+            width,
+            height,
+            commands: commands.iter().map(|command| command.to_string()).collect(),
+            frames: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+impl Backend for FakeBackend {
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        self.frames.push(frame.clone());
+        Ok(())
+    }
+
+    fn read_command(&mut self) -> io::Result<Option<String>> {
+        Ok(self.commands.pop_front())
+    }
+}
@@ -0,0 +1,166 @@
+use entities::Faction;
+
+/// Colors which can be displayed by any ANSI terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    /// Returns the ANSI foreground color code.
+    pub fn ansi_code(self) -> u8 {
+        match self {
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+        }
+    }
+
+    /// Returns the color used to display the given faction.
+    pub fn faction(faction: &Faction) -> Color {
+        match *faction {
+            Faction::Empire => Color::Red,
+            Faction::Federation => Color::Blue,
+            Faction::Cartel => Color::Yellow,
+            Faction::Independent => Color::Green,
+        }
+    }
+}
+
+/// A single character on screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub symbol: char,
+    pub color: Option<Color>,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            symbol: ' ',
+            color: None,
+        }
+    }
+}
+
+/// A rectangular area of the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the area inside the border of this area.
+    pub fn inner(&self) -> Rect {
+        Rect::new(
+            self.x + 1,
+            self.y + 1,
+            self.width.saturating_sub(2),
+            self.height.saturating_sub(2),
+        )
+    }
+}
+
+/// A full screen of cells to be drawn by a backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Frame {
+    /// Creates a new blank frame of the given size.
+    pub fn new(width: usize, height: usize) -> Self {
+        Frame {
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
+        }
+    }
+
+    /// Returns the cells of the given row.
+    pub fn row(&self, y: usize) -> &[Cell] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    /// Returns the text of the given row, without colors.
+    #[cfg(test)]
+    pub fn row_text(&self, y: usize) -> String {
+        self.row(y).iter().map(|cell| cell.symbol).collect()
+    }
+
+    /// Sets the cell at the given position, ignoring positions off screen.
+    pub fn put(&mut self, x: usize, y: usize, symbol: char, color: Option<Color>) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = Cell { symbol, color };
+        }
+    }
+
+    /// Prints text starting at the given position, cut off at max_width.
+    pub fn print(
+        &mut self,
+        x: usize,
+        y: usize,
+        max_width: usize,
+        text: &str,
+        color: Option<Color>,
+    ) {
+        for (offset, symbol) in text.chars().take(max_width).enumerate() {
+            self.put(x + offset, y, symbol, color);
+        }
+    }
+
+    /// Draws a border around the given area, with the title on the top border.
+    pub fn draw_box(&mut self, area: Rect, title: &str) {
+        if area.width < 2 || area.height < 2 {
+            return;
+        }
+        let (right, bottom) = (area.x + area.width - 1, area.y + area.height - 1);
+        for x in area.x..=right {
+            self.put(x, area.y, '-', None);
+            self.put(x, bottom, '-', None);
+        }
+        for y in area.y..=bottom {
+            self.put(area.x, y, '|', None);
+            self.put(right, y, '|', None);
+        }
+        for &(x, y) in &[
+            (area.x, area.y),
+            (right, area.y),
+            (area.x, bottom),
+            (right, bottom),
+        ] {
+            self.put(x, y, '+', None);
+        }
+        self.print(
+            area.x + 2,
+            area.y,
+            area.width.saturating_sub(4),
+            &format!(" {} ", title),
+            Some(Color::Cyan),
+        );
+    }
+}
@@ -0,0 +1,524 @@
+use anyhow::{anyhow, Result};
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+use economy::Commodity;
+use entities::{Galaxy, Star, System, SystemFilter};
+use game::Game;
+use navigation::{Route, RouteObjective};
+use player::Player;
+use utils::Point;
+
+mod backend;
+mod frame;
+
+pub use self::backend::{AnsiBackend, Backend};
+use self::frame::{Color, Frame, Rect};
+
+/// Commands which can be entered at the prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Help,
+    Jump(String),
+    Route(String),
+    Next,
+    Buy(u64, Commodity),
+    Sell(u64, Commodity),
+    Refuel,
+    Trades(usize),
+    Save,
+    Quit,
+}
+
+impl Command {
+    /// Number of jumps searched for trades when none is given.
+    const DEFAULT_TRADE_JUMPS: usize = 5;
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    /// Parses a command line, e.g "buy 5 consumer items" or "jump Levich I".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let name = words.next().unwrap_or("").to_lowercase();
+        let rest = words.collect::<Vec<_>>();
+
+        let trade = |rest: &[&str]| -> Result<(u64, Commodity), String> {
+            match rest.split_first() {
+                Some((amount, commodity)) if !commodity.is_empty() => Ok((
+                    amount
+                        .parse()
+                        .map_err(|_| format!("Invalid amount: {}", amount))?,
+                    commodity.join(" ").parse()?,
+                )),
+                _ => Err(format!("Usage: {} <amount> <commodity>", name)),
+            }
+        };
+        let system = |rest: &[&str]| -> Result<String, String> {
+            match rest.len() {
+                0 => Err(format!("Usage: {} <system name>", name)),
+                _ => Ok(rest.join(" ")),
+            }
+        };
+
+        match name.as_str() {
+            "help" | "?" => Ok(Command::Help),
+            "jump" => Ok(Command::Jump(system(&rest)?)),
+            "route" => Ok(Command::Route(system(&rest)?)),
+            "next" => Ok(Command::Next),
+            "buy" => trade(&rest).map(|(amount, commodity)| Command::Buy(amount, commodity)),
+            "sell" => trade(&rest).map(|(amount, commodity)| Command::Sell(amount, commodity)),
+            "refuel" => Ok(Command::Refuel),
+            "trades" => match rest.as_slice() {
+                [] => Ok(Command::Trades(Command::DEFAULT_TRADE_JUMPS)),
+                [jumps] => jumps
+                    .parse()
+                    .map(Command::Trades)
+                    .map_err(|_| format!("Invalid number of jumps: {}", jumps)),
+                _ => Err(format!("Usage: {} [max jumps]", name)),
+            },
+            "save" => Ok(Command::Save),
+            "quit" | "exit" => Ok(Command::Quit),
+            _ => Err(format!("Unknown command: {}, type help for help", s)),
+        }
+    }
+}
+
+/// Full screen terminal interface, driving a game through commands.
+pub struct Tui<B: Backend> {
+    backend: B,
+    game: Arc<Game>,
+    save_path: PathBuf,
+    route: Option<Route>,
+    log: Vec<String>,
+}
+
+impl<B: Backend> Tui<B> {
+    /// Width of the map in light years per jump range of the player's ship.
+    const MAP_JUMPS: f64 = 4.;
+    /// Maximum number of trades listed by the trades command.
+    const LISTED_TRADES: usize = 5;
+
+    /// Creates a new interface for the game, which is saved to save_path.
+    pub fn new(backend: B, game: Arc<Game>, save_path: PathBuf) -> Self {
+        Tui {
+            backend,
+            game,
+            save_path,
+            route: None,
+            log: vec![String::from("Welcome commander, type help for help.")],
+        }
+    }
+
+    /// Runs the interface until the player quits, saving the game on exit.
+    pub fn run(&mut self) -> Result<()> {
+        let result = self.event_loop();
+        self.backend.restore()?;
+        result?;
+        self.game.save(&self.save_path)
+    }
+
+    /// Draws the screen and handles commands until quit or end of input.
+    fn event_loop(&mut self) -> Result<()> {
+        loop {
+            self.game.update();
+            let frame = self.render();
+            self.backend.draw(&frame)?;
+
+            let line = match self.backend.read_command()? {
+                Some(line) => line,
+                None => return Ok(()),
+            };
+            if line.is_empty() {
+                continue;
+            }
+            match line.parse() {
+                Ok(Command::Quit) => return Ok(()),
+                Ok(command) => {
+                    if let Err(e) = self.execute(command) {
+                        self.log.push(e.to_string());
+                    }
+                }
+                Err(e) => self.log.push(e),
+            }
+        }
+    }
+
+    /// Performs the given command against the game.
+    fn execute(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::Help => self.log.extend(
+                [
+                    "jump <system>: jump to a system within jump range",
+                    "route <system>: plan a route to a system, next: jump along it",
+                    "buy/sell <amount> <commodity>: trade at the current system",
+                    "refuel: fill up the tank",
+                    "trades [max jumps]: list profitable trades, within 5 jumps by default",
+                    "save: save the game, quit: save and exit",
+                ]
+                .iter()
+                .map(|line| line.to_string()),
+            ),
+            Command::Jump(name) => {
+                let destination = self.find_system(&name)?;
+                self.game.jump(&destination.location)?;
+                self.log.push(format!("Jumped to {}", destination.name));
+            }
+            Command::Route(name) => {
+                let destination = self.find_system(&name)?;
+                let route = self
+                    .game
+                    .route(&destination.location, RouteObjective::FewestJumps)?;
+                self.log.push(format!(
+                    "Route to {}: {} jumps, {:.2} ly",
+                    destination.name,
+                    route.jumps(),
+                    route.distance
+                ));
+                self.route = Some(route);
+            }
+            Command::Next => {
+                let location = self.game.player.lock().unwrap().location();
+                let next = self.route.as_ref().and_then(|route| {
+                    route
+                        .systems
+                        .iter()
+                        .skip_while(|system| **system != location)
+                        .nth(1)
+                        .cloned()
+                });
+                match next {
+                    Some(next) => {
+                        self.game.jump(&next)?;
+                        let galaxy = self.game.galaxy.lock().unwrap();
+                        self.log
+                            .push(format!("Jumped to {}", galaxy.system(&next).unwrap().name));
+                    }
+                    None => self.log.push(String::from("No route to follow")),
+                }
+            }
+            Command::Buy(amount, commodity) => {
+                let trade = self.game.buy(&commodity, amount)?;
+                self.log.push(format!(
+                    "Bought {} {} for {} credits",
+                    trade.amount, trade.commodity, trade.total
+                ));
+            }
+            Command::Sell(amount, commodity) => {
+                let trade = self.game.sell(&commodity, amount)?;
+                self.log.push(format!(
+                    "Sold {} {} for {} credits",
+                    trade.amount, trade.commodity, trade.total
+                ));
+            }
+            Command::Refuel => {
+                let amount = self.game.player.lock().unwrap().refuel();
+                self.log.push(format!("Bought {:.2} fuel", amount));
+            }
+            Command::Trades(max_jumps) => {
+                let runs = self.game.best_trades(max_jumps);
+                let galaxy = self.game.galaxy.lock().unwrap();
+                if runs.is_empty() {
+                    self.log.push(String::from("No profitable trades found"));
+                }
+                for run in runs.iter().take(Tui::<B>::LISTED_TRADES) {
+                    self.log.push(format!(
+                        "{} to {} ({} jumps): profit {}",
+                        run.commodity,
+                        galaxy.system(&run.destination).unwrap().name,
+                        run.jumps,
+                        run.profit
+                    ));
+                }
+            }
+            Command::Save => {
+                self.game.save(&self.save_path)?;
+                self.log
+                    .push(format!("Game saved to {}", self.save_path.display()));
+            }
+            Command::Quit => {}
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the system with the given name.
+    fn find_system(&self, name: &str) -> Result<System> {
+        self.game
+            .galaxy
+            .lock()
+            .unwrap()
+            .system_by_name(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No system named {}", name))
+    }
+
+    /// Renders the full screen.
+    fn render(&self) -> Frame {
+        let (width, height) = self.backend.size();
+        let mut frame = Frame::new(width, height);
+
+        let galaxy = self.game.galaxy.lock().unwrap();
+        let player = self.game.player.lock().unwrap();
+        let system = galaxy.system(&player.location());
+
+        // Status bar.
+        frame.print(
+            0,
+            0,
+            width,
+            &format!(
+                " GEMINI | {} | Credits: {} | Fuel: {:.1}/{:.1} | Cargo: {}/{} | {}",
+                self.game.updated().format("%Y-%m-%d"),
+                player.credits(),
+                player.ship().fuel(),
+                player.ship().fuel_capacity,
+                player.ship().cargo_used(),
+                player.ship().cargo_capacity,
+                system.map_or("Deep space", |system| &system.name)
+            ),
+            Some(Color::White),
+        );
+
+        // System and market on the left, galaxy map and log on the right.
+        let left = width / 2;
+        let body = height.saturating_sub(1);
+        let market_height = (Commodity::values().len() + 3).min(body * 2 / 3);
+        let log_height = body / 3;
+        let system_area = Rect::new(0, 1, left, body - market_height);
+        let market_area = Rect::new(0, 1 + body - market_height, left, market_height);
+        let map_area = Rect::new(left, 1, width - left, body - log_height);
+        let log_area = Rect::new(left, 1 + body - log_height, width - left, log_height);
+
+        if let Some(system) = system {
+            self.render_system(&mut frame, system_area, system);
+            self.render_market(&mut frame, market_area, system, &player);
+        }
+        self.render_log(&mut frame, log_area);
+        self.render_map(&mut frame, map_area, &galaxy, &player);
+        frame
+    }
+
+    /// Renders the star and planets of the current system.
+    fn render_system(&self, frame: &mut Frame, area: Rect, system: &System) {
+        frame.draw_box(area, &system.name);
+        let inner = area.inner();
+        let mut lines = vec![(
+            format!(
+                "{}, {} security, {}, reputation {}",
+                system.faction, system.security, system.state, system.reputation
+            ),
+            Some(Color::faction(&system.faction)),
+        )];
+        lines.extend(system.stars.iter().enumerate().map(|(index, star)| {
+            (
+                format!(
+                    "Star {}: {}, {:.2} solar masses, luminosity {:.2}, {:.0} K",
+                    Star::designation(index),
+                    star.class,
+                    star.mass,
+                    star.luminosity,
+                    star.temperature
+                ),
+                Some(Color::Yellow),
+            )
+        }));
+        for planet in &system.satelites {
+            lines.push((
+                format!(
+                    "{}: {}, {}, {:.0} K, {:.2} g, {}, habitability {:.2}, orbits {} every {:.0} days",
+                    planet.name,
+                    planet.planet_type,
+                    planet.economic_type,
+                    planet.surface_temperature,
+                    planet.gravity,
+                    planet.atmosphere,
+                    planet.habitability,
+                    planet.orbit,
+                    planet.orbital_period
+                ),
+                None,
+            ));
+            lines.extend(planet.moons.iter().map(|moon| {
+                (
+                    format!(
+                        "  {}: {} moon, {}",
+                        moon.name, moon.planet_type, moon.economic_type
+                    ),
+                    None,
+                )
+            }));
+        }
+        lines.extend(system.belts.iter().map(|belt| {
+            (
+                format!(
+                    "{}: asteroid belt, {}, orbits {}",
+                    belt.name, belt.economic_type, belt.orbit
+                ),
+                None,
+            )
+        }));
+        lines.extend(system.stations.iter().map(|station| {
+            (
+                format!("{}: station, {}", station.name, station.economic_type),
+                Some(Color::Cyan),
+            )
+        }));
+
+        for (row, (line, color)) in lines.iter().take(inner.height).enumerate() {
+            frame.print(inner.x, inner.y + row, inner.width, line, *color);
+        }
+    }
+
+    /// Renders the commodity prices and stock of the current system.
+    fn render_market(&self, frame: &mut Frame, area: Rect, system: &System, player: &Player) {
+        let title = system
+            .market_station()
+            .map_or(String::from("Market"), |station| {
+                format!("Market at {}", station.name)
+            });
+        frame.draw_box(area, &title);
+        let inner = area.inner();
+        let listings = self.game.economy.lock().unwrap().listings(system);
+
+        frame.print(
+            inner.x,
+            inner.y,
+            inner.width,
+            &format!(
+                "{:<22}{:>10}{:>10}{:>6}",
+                "Commodity", "Price", "Stock", "Hold"
+            ),
+            Some(Color::Cyan),
+        );
+        for (row, (commodity, price, stock)) in listings
+            .iter()
+            .take(inner.height.saturating_sub(1))
+            .enumerate()
+        {
+            frame.print(
+                inner.x,
+                inner.y + 1 + row,
+                inner.width,
+                &format!(
+                    "{:<22}{:>10}{:>10}{:>6}",
+                    commodity.to_string(),
+                    price,
+                    stock,
+                    player.ship().cargo(commodity)
+                ),
+                None,
+            );
+        }
+    }
+
+    /// Renders the most recent log messages.
+    fn render_log(&self, frame: &mut Frame, area: Rect) {
+        frame.draw_box(area, "Log");
+        let inner = area.inner();
+        let skip = self.log.len().saturating_sub(inner.height);
+        for (row, line) in self.log.iter().skip(skip).enumerate() {
+            frame.print(inner.x, inner.y + row, inner.width, line, None);
+        }
+    }
+
+    /// Renders the systems around the player, colored by faction, along with
+    /// the planned route.
+    fn render_map(&self, frame: &mut Frame, area: Rect, galaxy: &Galaxy, player: &Player) {
+        frame.draw_box(area, "Galaxy");
+        let inner = area.inner();
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+
+        // Terminal cells are about twice as high as they are wide.
+        let center = player.location();
+        let radius = player.ship().jump_range * Tui::<B>::MAP_JUMPS;
+        let columns_per_ly = (inner.width as f64 / (2. * radius)).min(inner.height as f64 / radius);
+        let to_cell = |location: &Point| {
+            let x = (location.x - center.x) * columns_per_ly + inner.width as f64 / 2.;
+            let y = (location.y - center.y) * columns_per_ly / 2. + inner.height as f64 / 2.;
+            if x >= 0. && y >= 0. && (x as usize) < inner.width && (y as usize) < inner.height {
+                Some((inner.x + x as usize, inner.y + y as usize))
+            } else {
+                None
+            }
+        };
+
+        let view_radius = radius * 2.;
+        for system in galaxy.systems_within(&center, view_radius, &SystemFilter::default()) {
+            if let Some((x, y)) = to_cell(&system.location) {
+                frame.put(x, y, '*', Some(Color::faction(&system.faction)));
+            }
+        }
+        if let Some(ref route) = self.route {
+            for location in &route.systems {
+                if let Some((x, y)) = to_cell(location) {
+                    frame.put(x, y, '+', Some(Color::Magenta));
+                }
+            }
+        }
+        if let Some((x, y)) = to_cell(&center) {
+            frame.put(x, y, '@', Some(Color::White));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::backend::FakeBackend;
+    use super::*;
+    use config::GameConfig;
+    use economy::Economy;
+    use generators::generate_galaxy;
+    use std::{env, fs};
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            "buy 5 consumer items".parse(),
+            Ok(Command::Buy(5, Commodity::ConsumerItem))
+        );
+        assert_eq!(
+            "jump Levich I".parse(),
+            Ok(Command::Jump(String::from("Levich I")))
+        );
+        assert_eq!("trades".parse(), Ok(Command::Trades(5)));
+        assert_eq!("trades 2".parse(), Ok(Command::Trades(2)));
+        assert!("trades many".parse::<Command>().is_err());
+        assert!("sell food".parse::<Command>().is_err());
+        assert!("fly".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let config = GameConfig {
+            number_of_systems: 100,
+            number_of_sectors: 2,
+            ..GameConfig::default()
+        };
+        let game = Game::new();
+        let galaxy = generate_galaxy(&config).unwrap();
+        *game.economy.lock().unwrap() = Economy::new(&galaxy, &config.economy);
+        let start = galaxy.systems().next().unwrap().clone();
+        *game.player.lock().unwrap() = Player::new(start.location);
+        *game.galaxy.lock().unwrap() = galaxy;
+
+        let path = env::temp_dir().join(format!("gemini-tui-{}.sav", std::process::id()));
+        let backend = FakeBackend::new(100, 30, &["help", "sell 1 food", "fly", "quit"]);
+        let mut tui = Tui::new(backend, game, path.clone());
+        tui.run().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let frames = &tui.backend.frames;
+        assert_eq!(frames.len(), 4);
+        let last = frames.last().unwrap();
+        let screen = (0..last.height)
+            .map(|y| last.row_text(y))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(screen.contains(&start.name));
+        assert!(screen.contains("Market"));
+        assert!(screen.contains("Insufficient cargo, only 0 in hold"));
+        assert!(screen.contains("Unknown command: fly"));
+    }
+}
@@ -29,11 +29,17 @@ impl Point {
         ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
     }
 
+    /// Size of the coarsest cell of the grid the hash is computed on, points
+    /// closer than this may share a hash.
+    pub const HASH_CELL: f64 = 1. / 19_349_663f64;
+
     /// Returns the hash of the point coordinates.
     /// Hash based on algorithm used is presented in the paper:
     /// Optimized Spatial Hashing for Collision Detection of Deformable Objects.
+    /// Coordinates are converted through signed integers so that negative
+    /// coordinates do not all saturate to zero.
     pub fn hash(&self) -> u64 {
-        (self.x * 73_856_093f64) as u64 ^ (self.y * 19_349_663f64) as u64
+        (self.x * 73_856_093f64) as i64 as u64 ^ (self.y * 19_349_663f64) as i64 as u64
     }
 }
 
@@ -101,11 +107,12 @@ impl PointN for Point {
     }
 }
 
-/// Point with weight associated so that it can be ordered.
+/// Point with weight associated so that it can be ordered, the point with the
+/// lowest weight is the greatest, making a `BinaryHeap` a min-heap.
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct OrdPoint {
     pub point: Point,
-    pub weight: u32,
+    pub weight: f64,
 }
 
 impl Ord for OrdPoint {
@@ -128,6 +135,39 @@ impl PartialEq for OrdPoint {
 
 impl Eq for OrdPoint {}
 
+/// Returns the convex hull of the given points in counter clockwise order,
+/// using Andrew's monotone chain algorithm.
+pub fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| (a.x, a.y).partial_cmp(&(b.x, b.y)).unwrap());
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let mut hull = half_hull(points.iter());
+    hull.extend(half_hull(points.iter().rev()));
+    hull
+}
+
+/// Returns the half of the convex hull turning counter clockwise through the
+/// sorted points, excluding the last point.
+fn half_hull<'a, I: Iterator<Item = &'a Point>>(points: I) -> Vec<Point> {
+    // Z component of the cross product of (a - o) and (b - o).
+    let cross =
+        |o: &Point, a: &Point, b: &Point| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+
+    let mut hull: Vec<Point> = vec![];
+    for point in points {
+        while hull.len() >= 2 && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], point) <= 0. {
+            hull.pop();
+        }
+        hull.push(*point);
+    }
+    hull.pop();
+    hull
+}
+
 /// Returns the edit distance between strings `a` and `b` using Levenshtein
 /// distance.
 /// The runtime complexity is `O(m*n)`, where `m` and `n` are the
@@ -177,6 +217,28 @@ pub fn edit_distance(a: &str, b: &str) -> i32 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_convex_hull() {
+        let points = vec![
+            Point::new(0., 0.),
+            Point::new(1., 1.),
+            Point::new(2., 0.),
+            Point::new(2., 2.),
+            Point::new(0., 2.),
+            Point::new(1., 0.),
+        ];
+        assert_eq!(
+            convex_hull(&points),
+            vec![
+                Point::new(0., 0.),
+                Point::new(2., 0.),
+                Point::new(2., 2.),
+                Point::new(0., 2.),
+            ]
+        );
+        assert_eq!(convex_hull(&points[..2]).len(), 2);
+    }
+
     #[test]
     fn test_edit_distance() {
         assert_eq!(edit_distance("foo", "foobar"), 3);